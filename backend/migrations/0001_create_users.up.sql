@@ -0,0 +1,6 @@
+-- IF NOT EXISTS adopts databases created before migrations were tracked.
+CREATE TABLE IF NOT EXISTS users (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    email TEXT NOT NULL
+);
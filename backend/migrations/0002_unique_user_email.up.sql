@@ -0,0 +1,3 @@
+-- Fails if existing rows already share an address; resolve those by hand first.
+UPDATE users SET email = btrim(email) WHERE email <> btrim(email);
+CREATE UNIQUE INDEX users_email_key ON users (lower(btrim(email)));
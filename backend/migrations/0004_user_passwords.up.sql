@@ -0,0 +1,2 @@
+-- argon2 PHC strings. Users without one exist only as records and cannot log in.
+ALTER TABLE users ADD COLUMN password_hash TEXT;
@@ -0,0 +1,5 @@
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'viewer'
+    CONSTRAINT users_role_check CHECK (role IN ('viewer', 'editor', 'admin'));
+
+-- Until now everyone who could log in could do everything.
+UPDATE users SET role = 'admin' WHERE password_hash IS NOT NULL;
@@ -0,0 +1,18 @@
+-- One row per change to a user, written in the same transaction as the
+-- change. There are no foreign keys: events outlive their actors and targets.
+CREATE TABLE audit_events (
+    id BIGSERIAL PRIMARY KEY,
+    actor_kind TEXT NOT NULL CHECK (actor_kind IN ('user', 'api_key')),
+    actor_id BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    target_id INTEGER NOT NULL,
+    before JSONB,
+    after JSONB,
+    request_id TEXT NOT NULL,
+    ip INET,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX audit_events_target_id_idx ON audit_events (target_id);
+CREATE INDEX audit_events_actor_idx ON audit_events (actor_kind, actor_id);
+CREATE INDEX audit_events_created_at_idx ON audit_events (created_at);
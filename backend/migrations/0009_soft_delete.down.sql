@@ -0,0 +1,11 @@
+DELETE FROM audit_events WHERE actor_kind = 'system';
+ALTER TABLE audit_events DROP CONSTRAINT audit_events_actor_kind_check;
+ALTER TABLE audit_events ADD CONSTRAINT audit_events_actor_kind_check
+    CHECK (actor_kind IN ('user', 'api_key'));
+
+-- Deleted users cannot be represented without the column.
+DELETE FROM users WHERE deleted_at IS NOT NULL;
+DROP INDEX users_deleted_at_idx;
+DROP INDEX users_email_key;
+CREATE UNIQUE INDEX users_email_key ON users (lower(btrim(email)));
+ALTER TABLE users DROP COLUMN deleted_at;
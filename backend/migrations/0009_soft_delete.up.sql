@@ -0,0 +1,13 @@
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Deleted users keep their address until they are purged, but it is free for
+-- someone else in the meantime.
+DROP INDEX users_email_key;
+CREATE UNIQUE INDEX users_email_key ON users (lower(btrim(email))) WHERE deleted_at IS NULL;
+
+CREATE INDEX users_deleted_at_idx ON users (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Purges are done by the server itself rather than by a user or API key.
+ALTER TABLE audit_events DROP CONSTRAINT audit_events_actor_kind_check;
+ALTER TABLE audit_events ADD CONSTRAINT audit_events_actor_kind_check
+    CHECK (actor_kind IN ('user', 'api_key', 'system'));
@@ -0,0 +1,3 @@
+DROP TRIGGER users_bump_version ON users;
+DROP FUNCTION users_bump_version();
+ALTER TABLE users DROP COLUMN version;
@@ -0,0 +1,4 @@
+DROP TRIGGER users_touch ON users;
+DROP FUNCTION users_touch();
+DROP INDEX users_created_at_idx;
+ALTER TABLE users DROP COLUMN created_at, DROP COLUMN updated_at;
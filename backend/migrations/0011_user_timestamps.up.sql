@@ -0,0 +1,20 @@
+-- Existing users get the time of the migration; when they were really
+-- created is not recorded anywhere.
+ALTER TABLE users
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE INDEX users_created_at_idx ON users (created_at);
+
+-- Like the version, kept right by the database whichever statement updates
+-- the row, and created_at cannot be overwritten.
+CREATE FUNCTION users_touch() RETURNS trigger AS $$
+BEGIN
+    NEW.created_at := OLD.created_at;
+    NEW.updated_at := now();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_touch BEFORE UPDATE ON users
+    FOR EACH ROW EXECUTE FUNCTION users_touch();
@@ -0,0 +1,234 @@
+use argon2::password_hash::rand_core::{ OsRng, RngCore };
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rocket::Route;
+use rocket::http::Status;
+use rocket::outcome::try_outcome;
+use rocket::request::{ self, FromRequest, Request };
+use rocket::response::status::Created;
+use rocket::serde::json::Json;
+use sha2::{ Digest, Sha256 };
+use shared::{ ApiKeyResponse, CreateApiKey, CreatedApiKey, Scope };
+use tokio_postgres::Row;
+
+use crate::auth::Admin;
+use crate::db::{ self, Db };
+use crate::error::ApiError;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Every key starts with this, so leaked keys are easy to grep for.
+const KEY_PREFIX: &str = "uk";
+
+// Unix timestamps keep the API free of date formats.
+const KEY_COLUMNS: &str =
+    "id, name, prefix, scopes,
+    floor(extract(epoch FROM created_at))::bigint AS created_at,
+    floor(extract(epoch FROM expires_at))::bigint AS expires_at,
+    floor(extract(epoch FROM last_used_at))::bigint AS last_used_at,
+    floor(extract(epoch FROM revoked_at))::bigint AS revoked_at";
+
+pub fn routes() -> Vec<Route> {
+    routes![create_api_key, get_api_keys, revoke_api_key]
+}
+
+/// A service authenticated by a live API key. As a guard it rejects unknown,
+/// expired and revoked keys with 401 Unauthorized.
+pub struct ApiKeyCaller {
+    pub id: i64,
+    pub scopes: Vec<Scope>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiKeyCaller {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let Some((prefix, key)) = req.headers().get_one(API_KEY_HEADER).and_then(parse_key) else {
+            return request::Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        let conn = try_outcome!(db::guard_connection(req).await);
+        let row = conn.query_opt(
+            "SELECT id, scopes FROM api_keys
+            WHERE prefix = $1 AND key_hash = $2
+                AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > now())",
+            &[&prefix, &key_hash(key)]
+        ).await;
+        let row = match row {
+            Ok(Some(row)) => row,
+            Ok(None) => {
+                return request::Outcome::Error((Status::Unauthorized, ()));
+            }
+            Err(e) => {
+                error!("Failed to check API key: {}", e);
+                return request::Outcome::Error((Status::InternalServerError, ()));
+            }
+        };
+        let id: i64 = row.get("id");
+
+        // Minute precision is plenty and spares busy keys a write per request.
+        let touched = conn.execute(
+            "UPDATE api_keys SET last_used_at = now()
+            WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < now() - interval '1 minute')",
+            &[&id]
+        ).await;
+        if let Err(e) = touched {
+            warn!("Failed to record use of API key {}: {}", id, e);
+        }
+
+        request::Outcome::Success(ApiKeyCaller { id, scopes: scopes_from_row(&row) })
+    }
+}
+
+/// Splits `uk_<prefix>_<secret>` into the prefix and the whole key.
+fn parse_key(key: &str) -> Option<(&str, &str)> {
+    let mut parts = key.splitn(3, '_');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(KEY_PREFIX), Some(prefix), Some(secret)) if !prefix.is_empty() && !secret.is_empty() =>
+            Some((prefix, key)),
+        _ => None,
+    }
+}
+
+/// Keys are random, so a fast digest is as good as a password hash here.
+fn key_hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+fn scopes_from_row(row: &Row) -> Vec<Scope> {
+    row.get::<_, Vec<String>>("scopes")
+        .iter()
+        .filter_map(|scope| Scope::parse(scope))
+        .collect()
+}
+
+fn api_key_from_row(row: &Row) -> ApiKeyResponse {
+    ApiKeyResponse {
+        id: row.get("id"),
+        name: row.get("name"),
+        prefix: row.get("prefix"),
+        scopes: scopes_from_row(row),
+        created_at: row.get("created_at"),
+        expires_at: row.get("expires_at"),
+        last_used_at: row.get("last_used_at"),
+        revoked_at: row.get("revoked_at"),
+    }
+}
+
+#[utoipa::path(
+    tag = "keys",
+    request_body = CreateApiKey,
+    responses(
+        (status = 201, description = "Key created; `key` is not shown again", body = CreatedApiKey),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only admins can manage API keys", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid key", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []))
+)]
+#[post("/api/keys", data = "<api_key>")]
+async fn create_api_key(
+    admin: Admin,
+    conn: Db,
+    api_key: Json<CreateApiKey>
+) -> Result<Created<Json<CreatedApiKey>>, ApiError> {
+    let api_key = api_key.into_inner().validated().map_err(ApiError::Validation)?;
+
+    let mut prefix = [0u8; 6];
+    OsRng.fill_bytes(&mut prefix);
+    let prefix = prefix.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    let key = format!("{}_{}_{}", KEY_PREFIX, prefix, URL_SAFE_NO_PAD.encode(secret));
+
+    let scopes = api_key.scopes.iter().map(Scope::as_str).collect::<Vec<_>>();
+    let expires_in = api_key.expires_in_days.map(|days| (days as f64) * 86400.0);
+    let row = conn.query_one(
+        &format!(
+            "INSERT INTO api_keys (name, prefix, key_hash, scopes, created_by, expires_at)
+            VALUES ($1, $2, $3, $4, $5, now() + make_interval(secs => $6))
+            RETURNING {}",
+            KEY_COLUMNS
+        ),
+        &[&api_key.name, &prefix, &key_hash(&key), &scopes, &admin.id, &expires_in]
+    ).await?;
+    let api_key = api_key_from_row(&row);
+
+    Ok(
+        Created::new(format!("/api/keys/{}", api_key.id)).body(
+            Json(CreatedApiKey { key, api_key })
+        )
+    )
+}
+
+#[utoipa::path(
+    tag = "keys",
+    responses(
+        (status = 200, description = "Every key, newest first, including expired and revoked ones", body = Vec<ApiKeyResponse>),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only admins can manage API keys", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []))
+)]
+#[get("/api/keys")]
+async fn get_api_keys(_admin: Admin, conn: Db) -> Result<Json<Vec<ApiKeyResponse>>, ApiError> {
+    let keys = conn
+        .query(&format!("SELECT {} FROM api_keys ORDER BY id DESC", KEY_COLUMNS), &[]).await?
+        .iter()
+        .map(api_key_from_row)
+        .collect();
+
+    Ok(Json(keys))
+}
+
+#[utoipa::path(
+    tag = "keys",
+    responses(
+        (status = 204, description = "Key revoked; revoking it again is not an error"),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only admins can manage API keys", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such key", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []))
+)]
+#[delete("/api/keys/<id>")]
+async fn revoke_api_key(_admin: Admin, conn: Db, id: i64) -> Result<Status, ApiError> {
+    match
+        conn.execute(
+            "UPDATE api_keys SET revoked_at = coalesce(revoked_at, now()) WHERE id = $1",
+            &[&id]
+        ).await?
+    {
+        0 => Err(ApiError::NotFound),
+        _ => Ok(Status::NoContent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_issued_keys() {
+        assert_eq!(parse_key("uk_0a1b2c3d4e5f_c2VjcmV0"), Some(("0a1b2c3d4e5f", "uk_0a1b2c3d4e5f_c2VjcmV0")));
+        // Secrets are URL-safe base64, which may itself contain underscores.
+        assert_eq!(parse_key("uk_0a1b2c3d4e5f_a_b-c_"), Some(("0a1b2c3d4e5f", "uk_0a1b2c3d4e5f_a_b-c_")));
+    }
+
+    #[test]
+    fn rejects_other_strings() {
+        for key in ["", "uk", "uk_", "uk_prefix", "uk_prefix_", "uk__secret", "xx_prefix_secret", "UK_prefix_secret"] {
+            assert_eq!(parse_key(key), None, "{}", key);
+        }
+    }
+
+    #[test]
+    fn hashes_are_stable_hex() {
+        let hash = key_hash("uk_0a1b2c3d4e5f_c2VjcmV0");
+        assert_eq!(hash.len(), 64);
+        assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(hash, key_hash("uk_0a1b2c3d4e5f_c2VjcmV0"));
+        assert_ne!(hash, key_hash("uk_0a1b2c3d4e5f_c2VjcmV1"));
+    }
+}
@@ -0,0 +1,271 @@
+use std::convert::Infallible;
+use std::net::IpAddr;
+
+use rocket::Route;
+use rocket::request::{ self, FromRequest, Request };
+use rocket::serde::json::Json;
+use shared::{ ActorKind, AuditAction, AuditEvent, FieldError, Page, UserResponse };
+use tokio_postgres::{ GenericClient, Row };
+use tokio_postgres::types::Json as SqlJson;
+
+use crate::auth::{ Admin, AuthUser, Caller };
+use crate::db::{ self, Db, Params, where_clause };
+use crate::error::ApiError;
+use crate::pagination::{ self, Cursor, Direction, PageParams };
+use crate::request_id::{ self, RequestId };
+
+pub fn routes() -> Vec<Route> {
+    routes![get_audit_events]
+}
+
+/// Who made a change.
+#[derive(Clone, Copy)]
+pub struct Actor {
+    kind: ActorKind,
+    id: i64,
+}
+
+impl Actor {
+    /// The server itself, or an operator at its command line.
+    pub const SYSTEM: Actor = Actor { kind: ActorKind::System, id: 0 };
+}
+
+impl From<&Caller> for Actor {
+    fn from(caller: &Caller) -> Self {
+        match caller {
+            Caller::User(user) => user.into(),
+            Caller::ApiKey(key) => Actor { kind: ActorKind::ApiKey, id: key.id },
+        }
+    }
+}
+
+impl From<&AuthUser> for Actor {
+    fn from(user: &AuthUser) -> Self {
+        Actor { kind: ActorKind::User, id: user.id.into() }
+    }
+}
+
+/// Where a change came from.
+pub struct AuditContext {
+    request_id: RequestId,
+    ip: Option<IpAddr>,
+}
+
+impl AuditContext {
+    /// A change made outside any request, under an id of its own.
+    pub fn system() -> AuditContext {
+        AuditContext { request_id: RequestId(request_id::generate()), ip: None }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuditContext {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let request_id = req.guard::<RequestId>().await.expect("RequestId is infallible");
+        // Not `client_ip()`, which takes any caller's word for it from the
+        // X-Real-IP header.
+        let ip = req.remote().map(|remote| remote.ip());
+        request::Outcome::Success(AuditContext { request_id, ip })
+    }
+}
+
+/// Records a change to user `target_id`. Call it inside the transaction
+/// making the change, so that either both are stored or neither is.
+pub async fn record(
+    client: &impl GenericClient,
+    context: &AuditContext,
+    actor: Actor,
+    action: AuditAction,
+    target_id: i32,
+    before: Option<&UserResponse>,
+    after: Option<&UserResponse>
+) -> Result<(), tokio_postgres::Error> {
+    client.execute(
+        "INSERT INTO audit_events (actor_kind, actor_id, action, target_id, before, after, request_id, ip)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        &[
+            &actor.kind.as_str(),
+            &actor.id,
+            &action.as_str(),
+            &target_id,
+            &before.map(SqlJson),
+            &after.map(SqlJson),
+            &context.request_id.0,
+            &context.ip,
+        ]
+    ).await?;
+
+    Ok(())
+}
+
+/// Records a `user.create` for each of `users` in one statement, for imports
+/// that would otherwise spend a round trip per row on [`record`].
+pub async fn record_creates(
+    client: &impl GenericClient,
+    context: &AuditContext,
+    actor: Actor,
+    users: &[UserResponse]
+) -> Result<(), tokio_postgres::Error> {
+    let ids: Vec<i32> = users.iter().map(|user| user.id).collect();
+    let afters: Vec<SqlJson<&UserResponse>> = users.iter().map(SqlJson).collect();
+    client.execute(
+        "INSERT INTO audit_events (actor_kind, actor_id, action, target_id, after, request_id, ip)
+        SELECT $1, $2, $3, target_id, after, $6, $7
+        FROM unnest($4::integer[], $5::jsonb[]) AS created (target_id, after)",
+        &[
+            &actor.kind.as_str(),
+            &actor.id,
+            &AuditAction::UserCreate.as_str(),
+            &ids,
+            &afters,
+            &context.request_id.0,
+            &context.ip,
+        ]
+    ).await?;
+
+    Ok(())
+}
+
+fn audit_event_from_row(row: &Row) -> AuditEvent {
+    AuditEvent {
+        id: row.get("id"),
+        actor_kind: ActorKind::parse(row.get("actor_kind")).expect("Checked by the table constraint"),
+        actor_id: row.get("actor_id"),
+        actor_name: row.get("actor_name"),
+        action: AuditAction::parse(row.get("action")).expect("Only written by record"),
+        target_id: row.get("target_id"),
+        before: row.get::<_, Option<SqlJson<_>>>("before").map(|json| json.0),
+        after: row.get::<_, Option<SqlJson<_>>>("after").map(|json| json.0),
+        request_id: row.get("request_id"),
+        ip: row.get("ip"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn invalid(field: &str, message: &str) -> ApiError {
+    ApiError::Validation(vec![FieldError::new(field, "invalid", message)])
+}
+
+/// Newest first.
+const SORT: &str = "-id";
+
+#[utoipa::path(
+    tag = "audit",
+    params(
+        ("actor_kind" = Option<String>, Query, description = "`user`, `api_key` or `system`"),
+        ("actor_id" = Option<i64>, Query, description = "Id of the user or API key that made the change"),
+        ("action" = Option<String>, Query, description = "`user.create`, `user.update`, `user.set_role`, `user.delete`, `user.restore`, `user.purge` or `user.set_password`"),
+        ("target_id" = Option<i32>, Query, description = "Id of the user changed"),
+        ("request_id" = Option<String>, Query, description = "`X-Request-Id` of the request that made the change"),
+        ("since" = Option<i64>, Query, description = "Unix timestamp; only events at or after it"),
+        ("until" = Option<i64>, Query, description = "Unix timestamp; only events before it"),
+        PageParams
+    ),
+    responses(
+        (status = 200, description = "One page of events, newest first", body = AuditPage),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only admins can read the audit log", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid parameters", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []))
+)]
+#[get(
+    "/api/audit?<actor_kind>&<actor_id>&<action>&<target_id>&<request_id>&<since>&<until>&<page..>"
+)]
+#[allow(clippy::too_many_arguments)]
+async fn get_audit_events(
+    _admin: Admin,
+    conn: Db,
+    actor_kind: Option<&str>,
+    actor_id: Option<i64>,
+    action: Option<&str>,
+    target_id: Option<i32>,
+    request_id: Option<&str>,
+    since: Option<i64>,
+    until: Option<i64>,
+    page: PageParams
+) -> Result<Json<Page<AuditEvent>>, ApiError> {
+    let limit = page.limit()?;
+    let direction = page.direction()?;
+    let since = db::check_timestamp("since", since)?;
+    let until = db::check_timestamp("until", until)?;
+
+    let mut params = Params::default();
+    let mut conditions = Vec::new();
+    if let Some(kind) = actor_kind {
+        let kind = ActorKind::parse(kind).ok_or_else(|| invalid("actor_kind", "Unknown actor kind"))?;
+        conditions.push(format!("e.actor_kind = {}", params.push(kind.as_str())));
+    }
+    if let Some(actor_id) = actor_id {
+        conditions.push(format!("e.actor_id = {}", params.push(actor_id)));
+    }
+    if let Some(action) = action {
+        let action = AuditAction::parse(action).ok_or_else(|| invalid("action", "Unknown action"))?;
+        conditions.push(format!("e.action = {}", params.push(action.as_str())));
+    }
+    if let Some(target_id) = target_id {
+        conditions.push(format!("e.target_id = {}", params.push(target_id)));
+    }
+    if let Some(request_id) = request_id {
+        conditions.push(format!("e.request_id = {}", params.push(request_id.to_string())));
+    }
+    if let Some(since) = since {
+        conditions.push(format!("e.created_at >= to_timestamp({})", params.push(since as f64)));
+    }
+    if let Some(until) = until {
+        conditions.push(format!("e.created_at < to_timestamp({})", params.push(until as f64)));
+    }
+
+    let total = conn
+        .query_one(
+            &format!("SELECT count(*) FROM audit_events e{}", where_clause(&conditions)),
+            &params.as_refs()
+        ).await?
+        .get(0);
+
+    // Walking backwards from a cursor means walking towards newer events.
+    let backwards = matches!(direction, Direction::Before(_));
+    if let Direction::After(cursor) | Direction::Before(cursor) = &direction {
+        if cursor.sort != SORT || cursor.key.is_some() {
+            let field = if backwards { "before" } else { "after" };
+            return Err(invalid(field, "Cursor does not match the requested sort"));
+        }
+        let op = if backwards { ">" } else { "<" };
+        conditions.push(format!("e.id {} {}", op, params.push(cursor.id)));
+    }
+    let order = if backwards { "ASC" } else { "DESC" };
+    let fetch = params.push(limit + 1);
+
+    let events = conn
+        .query(
+            &format!(
+                "SELECT e.id, e.actor_kind, e.actor_id, coalesce(u.name, k.name) AS actor_name,
+                    e.action, e.target_id, e.before, e.after, e.request_id, host(e.ip) AS ip,
+                    floor(extract(epoch FROM e.created_at))::bigint AS created_at
+                FROM audit_events e
+                LEFT JOIN users u ON e.actor_kind = 'user' AND u.id = e.actor_id
+                LEFT JOIN api_keys k ON e.actor_kind = 'api_key' AND k.id = e.actor_id{}
+                ORDER BY e.id {}
+                LIMIT {}",
+                where_clause(&conditions),
+                order,
+                fetch
+            ),
+            &params.as_refs()
+        ).await?
+        .iter()
+        .map(audit_event_from_row)
+        .collect::<Vec<_>>();
+
+    Ok(
+        Json(
+            pagination::page_from_rows(events, limit, &direction, total, |event| Cursor {
+                sort: SORT.to_string(),
+                key: None,
+                id: event.id,
+            })
+        )
+    )
+}
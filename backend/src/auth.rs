@@ -0,0 +1,281 @@
+use std::sync::OnceLock;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use argon2::{ Argon2, PasswordHash, PasswordHasher, PasswordVerifier };
+use argon2::password_hash::{ SaltString, rand_core::OsRng };
+use rocket::{ Route, State };
+use rocket::http::{ Cookie, CookieJar, SameSite, Status };
+use rocket::outcome::try_outcome;
+use rocket::request::{ self, FromRequest, Request };
+use rocket::serde::json::Json;
+use rocket::time::{ Duration, OffsetDateTime };
+use shared::{ Login, Role, Scope, UserResponse };
+use shared::validation::PASSWORD_MAX_LEN;
+use tokio_postgres::Client;
+
+use crate::api_keys::{ self, ApiKeyCaller };
+use crate::config::AuthConfig;
+use crate::db::{ self, Db };
+use crate::error::ApiError;
+use crate::tokens::JwtKeys;
+use crate::users;
+
+/// Private (encrypted and authenticated) cookie holding `<user id>:<expiry>`,
+/// the expiry being a Unix timestamp. It is checked server-side because the
+/// cookie's own `Expires` is up to the browser.
+const SESSION_COOKIE: &str = "session";
+
+pub fn routes() -> Vec<Route> {
+    routes![login, logout, me]
+}
+
+/// The user making the request, identified by an `Authorization: Bearer`
+/// access token or else by the session cookie. As a guard it rejects
+/// anonymous requests with 401 Unauthorized.
+pub struct AuthUser {
+    pub id: i32,
+    pub role: Role,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthUser {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        // Access tokens are short-lived and trusted until they expire, so
+        // checking one takes no database round trip.
+        if let Some(authorization) = req.headers().get_one("Authorization") {
+            let keys = req.rocket().state::<JwtKeys>();
+            return match authorization.strip_prefix("Bearer ").zip(keys) {
+                Some((token, keys)) =>
+                    match keys.verify(token) {
+                        Some((id, role)) => request::Outcome::Success(AuthUser { id, role }),
+                        None => request::Outcome::Error((Status::Unauthorized, ())),
+                    }
+                None => request::Outcome::Error((Status::Unauthorized, ())),
+            };
+        }
+
+        let session = req.cookies()
+            .get_private(SESSION_COOKIE)
+            .and_then(|cookie| parse_session(cookie.value()))
+            .filter(|&(_, expires_at)| expires_at > unix_now());
+        let Some((id, _)) = session else {
+            return request::Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        // The account may have been deleted, locked out or given another role
+        // since the login.
+        let conn = try_outcome!(db::guard_connection(req).await);
+        match
+            conn.query_opt(
+                "SELECT role FROM users WHERE id = $1 AND password_hash IS NOT NULL AND deleted_at IS NULL",
+                &[&id]
+            ).await
+        {
+            Ok(Some(row)) => request::Outcome::Success(AuthUser { id, role: users::role_from_row(&row) }),
+            Ok(None) => request::Outcome::Error((Status::Unauthorized, ())),
+            Err(e) => {
+                error!("Failed to check session: {}", e);
+                request::Outcome::Error((Status::InternalServerError, ()))
+            }
+        }
+    }
+}
+
+/// Who is making a request: a user, or a service holding an API key sent as
+/// `X-Api-Key`. The key wins when both are present.
+pub enum Caller {
+    User(AuthUser),
+    ApiKey(ApiKeyCaller),
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Caller {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        if req.headers().contains(api_keys::API_KEY_HEADER) {
+            req.guard::<ApiKeyCaller>().await.map(Caller::ApiKey)
+        } else {
+            req.guard::<AuthUser>().await.map(Caller::User)
+        }
+    }
+}
+
+/// Guards admitting users whose role is at least `$role` and API keys with
+/// `$scope`. Anonymous requests get 401 Unauthorized and everyone else 403
+/// Forbidden.
+macro_rules! caller_guard {
+    ($name:ident, $role:expr, $scope:expr) => {
+        pub struct $name(pub Caller);
+
+        impl std::ops::Deref for $name {
+            type Target = Caller;
+
+            fn deref(&self) -> &Caller {
+                &self.0
+            }
+        }
+
+        #[rocket::async_trait]
+        impl<'r> FromRequest<'r> for $name {
+            type Error = ();
+
+            async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+                let caller = try_outcome!(req.guard::<Caller>().await);
+                let allowed = match &caller {
+                    Caller::User(user) => user.role >= $role,
+                    Caller::ApiKey(key) => key.scopes.contains(&$scope),
+                };
+                if allowed {
+                    request::Outcome::Success($name(caller))
+                } else {
+                    request::Outcome::Error((Status::Forbidden, ()))
+                }
+            }
+        }
+    };
+}
+
+caller_guard!(Viewer, Role::Viewer, Scope::UsersRead);
+caller_guard!(Editor, Role::Editor, Scope::UsersWrite);
+
+/// A logged-in admin. API keys are never admitted.
+pub struct Admin(pub AuthUser);
+
+impl std::ops::Deref for Admin {
+    type Target = AuthUser;
+
+    fn deref(&self) -> &AuthUser {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let user = try_outcome!(req.guard::<AuthUser>().await);
+        if user.role >= Role::Admin {
+            request::Outcome::Success(Admin(user))
+        } else {
+            request::Outcome::Error((Status::Forbidden, ()))
+        }
+    }
+}
+
+fn parse_session(value: &str) -> Option<(i32, u64)> {
+    let (id, expires_at) = value.split_once(':')?;
+    Some((id.parse().ok()?, expires_at.parse().ok()?))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("Clock is after 1970").as_secs()
+}
+
+/// Hashes a password into an argon2id PHC string with a random salt.
+pub fn hash_password(password: &str) -> String {
+    Argon2::default()
+        .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .expect("Default argon2 parameters are valid")
+        .to_string()
+}
+
+/// Checks `password` against `hash` off the async runtime, since argon2 is
+/// deliberately slow. Without a hash the work is still done against a dummy
+/// one, so unknown emails cannot be told apart by response time.
+async fn verify_password(password: String, hash: Option<String>) -> bool {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+    rocket::tokio::task
+        ::spawn_blocking(move || {
+            let known = hash.is_some();
+            let hash = hash.unwrap_or_else(|| DUMMY_HASH.get_or_init(|| hash_password("")).clone());
+            let matches = PasswordHash::new(&hash).is_ok_and(|parsed| {
+                Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok()
+            });
+            known && matches
+        }).await
+        .unwrap_or(false)
+}
+
+/// The user with `email`, if `password` is theirs.
+pub async fn authenticate(
+    client: &Client,
+    email: &str,
+    password: String
+) -> Result<UserResponse, ApiError> {
+    if password.len() > PASSWORD_MAX_LEN {
+        return Err(ApiError::InvalidCredentials);
+    }
+
+    let row = client.query_opt(
+        &format!(
+            "SELECT {}, password_hash FROM users
+            WHERE lower(btrim(email)) = lower(btrim($1)) AND deleted_at IS NULL",
+            users::USER_COLUMNS
+        ),
+        &[&email]
+    ).await?;
+    let hash = row.as_ref().and_then(|row| row.get::<_, Option<String>>("password_hash"));
+    if !verify_password(password, hash).await {
+        return Err(ApiError::InvalidCredentials);
+    }
+
+    Ok(users::user_from_row(&row.expect("Verified above")))
+}
+
+#[utoipa::path(
+    tag = "auth",
+    request_body = Login,
+    responses(
+        (status = 200, description = "Logged in; the session cookie is set", body = UserResponse),
+        (status = 401, description = "Unknown email or wrong password", body = Problem, content_type = "application/problem+json")
+    )
+)]
+#[post("/api/auth/login", data = "<login>")]
+async fn login(
+    conn: Db,
+    cookies: &CookieJar<'_>,
+    config: &State<AuthConfig>,
+    login: Json<Login>
+) -> Result<Json<UserResponse>, ApiError> {
+    let login = login.into_inner();
+    let user = authenticate(&conn, &login.email, login.password).await?;
+
+    let expires_at = unix_now() + config.session_ttl;
+    cookies.add_private(
+        Cookie::build((SESSION_COOKIE, format!("{}:{}", user.id, expires_at)))
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .secure(config.secure_cookies)
+            .expires(OffsetDateTime::now_utc() + Duration::seconds(config.session_ttl as i64))
+    );
+
+    Ok(Json(user))
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses((status = 204, description = "Logged out; the session cookie is cleared"))
+)]
+#[post("/api/auth/logout")]
+fn logout(cookies: &CookieJar<'_>) -> Status {
+    cookies.remove_private(SESSION_COOKIE);
+    Status::NoContent
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "The logged-in user", body = UserResponse),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []))
+)]
+#[get("/api/auth/me")]
+async fn me(user: AuthUser, conn: Db) -> Result<Json<UserResponse>, ApiError> {
+    users::get_user_from_db(&conn, user.id, false).await?.map(Json).ok_or(ApiError::NotFound)
+}
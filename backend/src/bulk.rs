@@ -0,0 +1,127 @@
+use rocket::Route;
+use rocket::data::{ Data, Limits, ToByteUnit };
+use rocket::http::Status;
+use rocket::serde::json::{ self, Json };
+use shared::{ BulkMode, BulkOperation, BulkRequest, BulkResponse, BulkResult, CreateUser, Role };
+use tokio_postgres::GenericClient;
+
+use crate::audit::{ Actor, AuditContext };
+use crate::auth::{ Caller, Editor };
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::etag::IfMatch;
+use crate::users;
+
+pub fn routes() -> Vec<Route> {
+    routes![bulk_users]
+}
+
+/// Batches outgrow the `json` limit, so they have their own `bulk` limit.
+async fn read_body(data: Data<'_>, limits: &Limits) -> Result<BulkRequest, ApiError> {
+    let body = data
+        .open(limits.get("bulk").unwrap_or(1.mebibytes()))
+        .into_bytes().await
+        .map_err(|_| ApiError::Http(Status::BadRequest))?;
+    if !body.is_complete() {
+        return Err(ApiError::Http(Status::PayloadTooLarge));
+    }
+
+    // Answer like Rocket's own JSON guard: 400 for broken JSON, 422 for JSON
+    // of the wrong shape.
+    json::from_slice(&body).map_err(|e| {
+        ApiError::Http(if e.is_data() { Status::UnprocessableEntity } else { Status::BadRequest })
+    })
+}
+
+/// Runs one operation with the same rules as its single-user route.
+async fn run(
+    client: &impl GenericClient,
+    context: &AuditContext,
+    actor: Actor,
+    can_delete: bool,
+    operation: BulkOperation
+) -> Result<BulkResult, ApiError> {
+    match operation {
+        BulkOperation::Create { name, email } => {
+            let user = (CreateUser { name, email }).validated().map_err(ApiError::Validation)?;
+            let user = users::insert_user(client, context, actor, &user).await?;
+            Ok(BulkResult { status: 201, user: Some(user), error: None })
+        }
+        BulkOperation::Update { id, version, changes } => {
+            let patch = changes.validated().map_err(ApiError::Validation)?;
+            let if_match = IfMatch::Versions(vec![version]);
+            let user = users::apply_patch(client, context, actor, id, &if_match, patch).await?;
+            Ok(BulkResult { status: 200, user: Some(user), error: None })
+        }
+        BulkOperation::Delete { id, version } => {
+            if !can_delete {
+                return Err(ApiError::Http(Status::Forbidden));
+            }
+            users::soft_delete(client, context, actor, id, &IfMatch::Versions(vec![version])).await?;
+            Ok(BulkResult { status: 204, user: None, error: None })
+        }
+    }
+}
+
+fn failed(error: ApiError) -> BulkResult {
+    let problem = error.into_problem();
+    BulkResult { status: problem.status, user: None, error: Some(problem) }
+}
+
+#[utoipa::path(
+    tag = "users",
+    request_body = BulkRequest,
+    responses(
+        (status = 200, description = "A result per operation; see `committed` for whether any was saved", body = BulkResponse),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only editors, admins and keys with users:write can change users; deletes also need an admin", body = Problem, content_type = "application/problem+json"),
+        (status = 413, description = "Body over the `bulk` limit", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Malformed body or too many operations", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []), ("api_key" = []))
+)]
+#[post("/api/users/bulk", data = "<data>")]
+async fn bulk_users(
+    caller: Editor,
+    mut conn: Db,
+    context: AuditContext,
+    limits: &Limits,
+    data: Data<'_>
+) -> Result<Json<BulkResponse>, ApiError> {
+    let request = read_body(data, limits).await?.validated().map_err(ApiError::Validation)?;
+    let actor = Actor::from(&*caller);
+    // Deletes are for admins only, as on DELETE /api/users/<id>.
+    let can_delete = matches!(&*caller, Caller::User(user) if user.role == Role::Admin);
+
+    // Each operation gets a savepoint, so a failed one is undone alone and the
+    // transaction stays usable for the rest.
+    let mut tx = conn.transaction().await?;
+    let mut results = Vec::with_capacity(request.operations.len());
+    let mut any_failed = false;
+    for operation in request.operations {
+        let savepoint = tx.savepoint("bulk_operation").await?;
+        match run(&savepoint, &context, actor, can_delete, operation).await {
+            Ok(result) => {
+                savepoint.commit().await?;
+                results.push(result);
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                any_failed = true;
+                results.push(failed(e));
+            }
+        }
+    }
+
+    let committed = !(any_failed && request.mode == BulkMode::Atomic);
+    if committed {
+        tx.commit().await?;
+    } else {
+        tx.rollback().await?;
+        for result in results.iter_mut().filter(|result| result.error.is_none()) {
+            *result = failed(ApiError::RolledBack);
+        }
+    }
+
+    Ok(Json(BulkResponse { committed, results }))
+}
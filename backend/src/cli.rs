@@ -0,0 +1,268 @@
+use std::fmt;
+use std::io::BufRead;
+
+use shared::{ AuditAction, FieldError, Role };
+use shared::validation;
+use tokio_postgres::Client;
+
+use crate::audit::{ self, Actor, AuditContext };
+use crate::auth;
+use crate::migrations::{ self, MigrationError, MigrationStatus };
+use crate::users::{ USER_COLUMNS, user_from_row };
+
+const USAGE: &str =
+    "Usage:
+    backend                      start the server
+    backend --migrate            apply pending migrations and exit
+    backend migrate up           same as --migrate
+    backend migrate down [N]     revert the last N (default 1) migrations
+    backend migrate status       list applied and pending migrations
+    backend create-user NAME EMAIL [ROLE]
+                                 create a user who can log in with a password read
+                                 from stdin; ROLE is viewer, editor or admin (default)
+    backend set-password EMAIL   read a password from stdin and let EMAIL log in with it";
+
+pub enum Command {
+    Serve,
+    MigrateUp,
+    MigrateDown(usize),
+    MigrateStatus,
+    CreateUser {
+        name: String,
+        email: String,
+        role: Role,
+    },
+    SetPassword(String),
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+    Migration(MigrationError),
+    Db(tokio_postgres::Error),
+    Input(std::io::Error),
+    Invalid(Vec<FieldError>),
+    UnknownUser(String),
+}
+
+impl From<MigrationError> for CommandError {
+    fn from(e: MigrationError) -> Self {
+        CommandError::Migration(e)
+    }
+}
+
+impl From<tokio_postgres::Error> for CommandError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        CommandError::Db(e)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Migration(e) => write!(f, "Migration failed: {}", e),
+            CommandError::Db(e) => write!(f, "{}", e),
+            CommandError::Input(e) => write!(f, "Failed to read the password: {}", e),
+            CommandError::Invalid(errors) => {
+                let messages = errors
+                    .iter()
+                    .map(|e| e.message.as_str())
+                    .collect::<Vec<_>>();
+                write!(f, "{}", messages.join("; "))
+            }
+            CommandError::UnknownUser(email) => write!(f, "No user with email {}", email),
+        }
+    }
+}
+
+impl Command {
+    pub fn from_args(args: &[String]) -> Result<Command, String> {
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+        match args.as_slice() {
+            [] => Ok(Command::Serve),
+            ["--migrate"] | ["migrate"] | ["migrate", "up"] => Ok(Command::MigrateUp),
+            ["migrate", "down"] => Ok(Command::MigrateDown(1)),
+            ["migrate", "down", steps] =>
+                steps
+                    .parse()
+                    .map(Command::MigrateDown)
+                    .map_err(|_| format!("invalid number of steps: {}\n\n{}", steps, USAGE)),
+            ["migrate", "status"] => Ok(Command::MigrateStatus),
+            ["create-user", name, email] =>
+                Ok(Command::CreateUser { name: name.to_string(), email: email.to_string(), role: Role::Admin }),
+            ["create-user", name, email, role] =>
+                Role::parse(role)
+                    .map(|role| Command::CreateUser { name: name.to_string(), email: email.to_string(), role })
+                    .ok_or_else(|| format!("invalid role: {}\n\n{}", role, USAGE)),
+            ["set-password", email] => Ok(Command::SetPassword(email.to_string())),
+            ["-h"] | ["--help"] | ["help"] => Err(USAGE.to_string()),
+            _ => Err(format!("unrecognized arguments: {}\n\n{}", args.join(" "), USAGE)),
+        }
+    }
+
+    /// Runs a one-off command. Not used for [`Command::Serve`].
+    pub async fn run(self, client: &mut Client) -> Result<(), CommandError> {
+        match self {
+            Command::Serve => {}
+            Command::MigrateUp => {
+                let applied = migrations::run_pending(client).await?;
+                if applied.is_empty() {
+                    println!("Database is up to date");
+                }
+                for migration in applied {
+                    println!("Applied {} {}", migration.version, migration.name);
+                }
+            }
+            Command::MigrateDown(steps) => {
+                for migration in migrations::revert(client, steps).await? {
+                    println!("Reverted {} {}", migration.version, migration.name);
+                }
+            }
+            Command::MigrateStatus => {
+                for status in migrations::status(client).await? {
+                    match status {
+                        MigrationStatus::Applied(m) =>
+                            println!(
+                                "applied  {:>4} {} (at {}, sha256 {})",
+                                m.version,
+                                m.name,
+                                m.applied_at,
+                                &m.checksum[..12]
+                            ),
+                        MigrationStatus::Pending(m) =>
+                            println!("pending  {:>4} {}", m.version, m.name),
+                    }
+                }
+            }
+            // Audited like the API's writes, as the system rather than a user.
+            Command::CreateUser { name, email, role } => {
+                let (name, email) = validation::validate_user(&name, &email).map_err(CommandError::Invalid)?;
+                let password_hash = read_password()?;
+                let tx = client.transaction().await?;
+                let row = tx.query_one(
+                    &format!(
+                        "INSERT INTO users (name, email, password_hash, role) VALUES ($1, $2, $3, $4) RETURNING {}",
+                        USER_COLUMNS
+                    ),
+                    &[&name, &email, &password_hash, &role.as_str()]
+                ).await?;
+                let user = user_from_row(&row);
+                audit::record(
+                    &tx,
+                    &AuditContext::system(),
+                    Actor::SYSTEM,
+                    AuditAction::UserCreate,
+                    user.id,
+                    None,
+                    Some(&user)
+                ).await?;
+                tx.commit().await?;
+                println!("Created {} {} ({})", role.as_str(), user.id, email);
+            }
+            Command::SetPassword(email) => {
+                let password_hash = read_password()?;
+                let tx = client.transaction().await?;
+                let before = tx
+                    .query_opt(
+                        &format!(
+                            "SELECT {} FROM users
+                            WHERE lower(btrim(email)) = lower(btrim($1)) AND deleted_at IS NULL
+                            FOR UPDATE",
+                            USER_COLUMNS
+                        ),
+                        &[&email]
+                    ).await?
+                    .map(|row| user_from_row(&row))
+                    .ok_or_else(|| CommandError::UnknownUser(email.clone()))?;
+                let row = tx.query_one(
+                    &format!("UPDATE users SET password_hash = $1 WHERE id = $2 RETURNING {}", USER_COLUMNS),
+                    &[&password_hash, &before.id]
+                ).await?;
+                let after = user_from_row(&row);
+                audit::record(
+                    &tx,
+                    &AuditContext::system(),
+                    Actor::SYSTEM,
+                    AuditAction::UserSetPassword,
+                    after.id,
+                    Some(&before),
+                    Some(&after)
+                ).await?;
+                tx.commit().await?;
+                println!("Password set for {}", email);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a password from the first line of stdin rather than argv, so that it
+/// stays out of shell history and process listings, and returns its hash.
+fn read_password() -> Result<String, CommandError> {
+    let mut password = String::new();
+    std::io::stdin().lock().read_line(&mut password).map_err(CommandError::Input)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+
+    let mut errors = Vec::new();
+    validation::validate_password(password, &mut errors);
+    if !errors.is_empty() {
+        return Err(CommandError::Invalid(errors));
+    }
+
+    Ok(auth::hash_password(password))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Command::from_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert!(matches!(parse(&[]), Ok(Command::Serve)));
+        assert!(matches!(parse(&["--migrate"]), Ok(Command::MigrateUp)));
+        assert!(matches!(parse(&["migrate"]), Ok(Command::MigrateUp)));
+        assert!(matches!(parse(&["migrate", "up"]), Ok(Command::MigrateUp)));
+        assert!(matches!(parse(&["migrate", "down"]), Ok(Command::MigrateDown(1))));
+        assert!(matches!(parse(&["migrate", "down", "3"]), Ok(Command::MigrateDown(3))));
+        assert!(matches!(parse(&["migrate", "status"]), Ok(Command::MigrateStatus)));
+        assert!(
+            matches!(parse(&["set-password", "ada@example.com"]), Ok(Command::SetPassword(email)) if email == "ada@example.com")
+        );
+    }
+
+    #[test]
+    fn create_user_defaults_to_admin() {
+        assert!(
+            matches!(
+                parse(&["create-user", "Ada", "ada@example.com"]),
+                Ok(Command::CreateUser { name, email, role: Role::Admin }) if name == "Ada" && email == "ada@example.com"
+            )
+        );
+        assert!(
+            matches!(parse(&["create-user", "Ada", "ada@example.com", "viewer"]), Ok(Command::CreateUser { role: Role::Viewer, .. }))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for (args, error) in [
+            (&["migrate", "down", "x"][..], "invalid number of steps: x"),
+            (&["migrate", "down", "-1"][..], "invalid number of steps: -1"),
+            (&["create-user", "Ada", "ada@example.com", "owner"][..], "invalid role: owner"),
+            (&["create-user", "Ada"][..], "unrecognized arguments: create-user Ada"),
+            (&["serve"][..], "unrecognized arguments: serve"),
+        ] {
+            let Err(message) = parse(args) else {
+                panic!("{:?} should not parse", args);
+            };
+            assert!(message.starts_with(error), "{}", message);
+            assert!(message.ends_with(USAGE));
+        }
+        assert!(matches!(parse(&["--help"]), Err(message) if message == USAGE));
+    }
+}
@@ -0,0 +1,165 @@
+use std::ops::{ Deref, DerefMut };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::time::Duration;
+
+use bb8::{ PooledConnection, RunError };
+use bb8_postgres::PostgresConnectionManager;
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use rocket::http::Status;
+use rocket::request::{ self, FromRequest, Request };
+use tokio_postgres::Client;
+use shared::FieldError;
+use tokio_postgres::types::ToSql;
+
+use crate::config::DatabaseConfig;
+use crate::error::ApiError;
+
+pub type Manager = PostgresConnectionManager<MakeTlsConnector>;
+pub type Pool = bb8::Pool<Manager>;
+
+/// Builds the pool. Broken connections are dropped and replaced on demand, and
+/// every checkout is validated first, so a Postgres restart only fails the
+/// requests that were in flight.
+pub async fn init(config: &DatabaseConfig) -> Result<Pool, tokio_postgres::Error> {
+    let tls = MakeTlsConnector::new(TlsConnector::new().expect("Failed to initialize TLS"));
+    let pg_config = config.pg_config().expect("Validated by AppConfig::from_figment");
+
+    Pool::builder()
+        .max_size(config.pool_size)
+        .min_idle(Some(config.min_idle))
+        .connection_timeout(Duration::from_secs(config.checkout_timeout))
+        .test_on_check_out(true)
+        .build(PostgresConnectionManager::new(pg_config, tls)).await
+}
+
+/// A connection checked out of the managed [`Pool`] for the duration of a
+/// request.
+///
+/// Handlers declare it after their auth guards, which check out a connection
+/// of their own with [`guard_connection`] and return it before `Db` takes one:
+/// a request holding a connection while it waits for a second lets concurrent
+/// requests use up the pool between them.
+pub struct Db(PooledConnection<'static, Manager>);
+
+/// Whether the request's `Db` guard has run.
+struct HandlerConnection(AtomicBool);
+
+async fn checkout(req: &Request<'_>) -> request::Outcome<Db, RunError<tokio_postgres::Error>> {
+    let pool = req.rocket().state::<Pool>().expect("Pool is managed at launch");
+
+    match pool.get_owned().await {
+        Ok(conn) => request::Outcome::Success(Db(conn)),
+        Err(e) => {
+            error!("Failed to check out a database connection: {}", e);
+            request::Outcome::Error((Status::ServiceUnavailable, e))
+        }
+    }
+}
+
+/// Checks out a connection for a request guard, to be dropped before the
+/// guard returns.
+pub async fn guard_connection(req: &Request<'_>) -> request::Outcome<Db, ()> {
+    debug_assert!(
+        !req.local_cache(|| HandlerConnection(AtomicBool::new(false))).0.load(Ordering::Relaxed),
+        "{} declares Db before a guard that needs a connection",
+        req.route().map_or("A route".to_string(), |route| route.uri.to_string())
+    );
+    checkout(req).await.map_error(|(status, _)| (status, ()))
+}
+
+impl Deref for Db {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.0
+    }
+}
+
+impl DerefMut for Db {
+    fn deref_mut(&mut self) -> &mut Client {
+        &mut self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Db {
+    type Error = RunError<tokio_postgres::Error>;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        req.local_cache(|| HandlerConnection(AtomicBool::new(false))).0.store(true, Ordering::Relaxed);
+        checkout(req).await
+    }
+}
+
+/// Positional parameters for SQL assembled at runtime; `push` returns the
+/// placeholder to splice into the statement.
+#[derive(Default)]
+pub struct Params(Vec<Box<dyn ToSql + Sync + Send>>);
+
+impl Params {
+    pub fn push(&mut self, value: impl ToSql + Sync + Send + 'static) -> String {
+        self.0.push(Box::new(value));
+        format!("${}", self.0.len())
+    }
+
+    pub fn as_refs(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.0
+            .iter()
+            .map(|param| param.as_ref() as &(dyn ToSql + Sync))
+            .collect()
+    }
+}
+
+/// ` WHERE a AND b ...`, or nothing without conditions.
+pub fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    }
+}
+
+/// Unix timestamps of 0001-01-01 and 9999-12-31 23:59:59 UTC, well inside
+/// what Postgres can store.
+pub const TIMESTAMP_MIN: i64 = -62_135_596_800;
+pub const TIMESTAMP_MAX: i64 = 253_402_300_799;
+
+/// Checks a Unix timestamp query parameter before it reaches
+/// `to_timestamp`, which fails outside the range Postgres can store.
+pub fn check_timestamp(field: &str, value: Option<i64>) -> Result<Option<i64>, ApiError> {
+    match value {
+        Some(value) if !(TIMESTAMP_MIN..=TIMESTAMP_MAX).contains(&value) =>
+            Err(
+                ApiError::Validation(
+                    vec![
+                        FieldError::new(
+                            field,
+                            "out_of_range",
+                            format!("Timestamp must be between {} and {}", TIMESTAMP_MIN, TIMESTAMP_MAX)
+                        )
+                    ]
+                )
+            ),
+        _ => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_outside_postgres_range_are_rejected() {
+        assert!(matches!(check_timestamp("since", None), Ok(None)));
+        assert!(matches!(check_timestamp("since", Some(TIMESTAMP_MIN)), Ok(Some(TIMESTAMP_MIN))));
+        assert!(matches!(check_timestamp("since", Some(TIMESTAMP_MAX)), Ok(Some(TIMESTAMP_MAX))));
+        for value in [i64::MIN, TIMESTAMP_MIN - 1, TIMESTAMP_MAX + 1, i64::MAX] {
+            let Err(ApiError::Validation(errors)) = check_timestamp("since", Some(value)) else {
+                panic!("{} should be out of range", value);
+            };
+            assert_eq!(errors[0].field, "since");
+            assert_eq!(errors[0].code, "out_of_range");
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use rocket::http::{ ContentType, Status };
+use rocket::request::Request;
+use rocket::response::{ self, Responder, Response };
+use rocket::serde::json::Json;
+use shared::{ FieldError, Problem, codes };
+use tokio_postgres::error::SqlState;
+
+/// Every failure a handler can report. Clients receive an RFC 7807
+/// `application/problem+json` body with a stable `code`; internal details are
+/// only logged.
+#[derive(Debug)]
+pub enum ApiError {
+    Db(tokio_postgres::Error),
+    Validation(Vec<FieldError>),
+    NotFound,
+    InvalidCredentials,
+    /// A refresh token that is unknown, spent, expired or revoked.
+    InvalidGrant,
+    Conflict {
+        code: &'static str,
+        detail: &'static str,
+    },
+    /// An `If-Match` naming a version other than the current one.
+    PreconditionFailed,
+    /// A bulk operation undone because another one in its batch failed.
+    RolledBack,
+    /// Failures raised by Rocket itself (bad JSON, payload limits, guards)
+    /// and rendered by the catchers.
+    Http(Status),
+}
+
+impl ApiError {
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::Db(_) => Status::InternalServerError,
+            ApiError::Validation(_) => Status::UnprocessableEntity,
+            ApiError::NotFound => Status::NotFound,
+            ApiError::InvalidCredentials => Status::Unauthorized,
+            ApiError::InvalidGrant => Status::BadRequest,
+            ApiError::Conflict { .. } => Status::Conflict,
+            ApiError::PreconditionFailed => Status::PreconditionFailed,
+            ApiError::RolledBack => Status::FailedDependency,
+            ApiError::Http(status) => *status,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::Db(_) => codes::INTERNAL_ERROR,
+            ApiError::Validation(_) => codes::VALIDATION_FAILED,
+            ApiError::NotFound => codes::NOT_FOUND,
+            ApiError::InvalidCredentials => codes::INVALID_CREDENTIALS,
+            ApiError::InvalidGrant => codes::INVALID_GRANT,
+            ApiError::Conflict { code, .. } => code,
+            ApiError::PreconditionFailed => codes::PRECONDITION_FAILED,
+            ApiError::RolledBack => codes::ROLLED_BACK,
+            ApiError::Http(status) =>
+                match status.code {
+                    400 => codes::BAD_REQUEST,
+                    401 => codes::UNAUTHORIZED,
+                    403 => codes::FORBIDDEN,
+                    404 => codes::NOT_FOUND,
+                    413 => codes::PAYLOAD_TOO_LARGE,
+                    415 => codes::UNSUPPORTED_MEDIA_TYPE,
+                    422 => codes::MALFORMED_BODY,
+                    428 => codes::PRECONDITION_REQUIRED,
+                    503 => codes::SERVICE_UNAVAILABLE,
+                    code if code >= 500 => codes::INTERNAL_ERROR,
+                    _ => codes::REQUEST_FAILED,
+                }
+        }
+    }
+
+    pub fn into_problem(self) -> Problem {
+        let status = self.status();
+        let code = self.code();
+
+        let (detail, errors) = match self {
+            ApiError::Db(e) => {
+                error!("Database error: {}", e);
+                (None, Vec::new())
+            }
+            ApiError::Validation(errors) => (Some("Validation failed".to_string()), errors),
+            ApiError::InvalidCredentials =>
+                (Some("Email or password is incorrect".to_string()), Vec::new()),
+            ApiError::InvalidGrant =>
+                (Some("Refresh token is invalid, expired or revoked".to_string()), Vec::new()),
+            ApiError::Conflict { detail, .. } => (Some(detail.to_string()), Vec::new()),
+            ApiError::PreconditionFailed =>
+                (Some("Changed since it was read; fetch it again".to_string()), Vec::new()),
+            ApiError::RolledBack =>
+                (Some("Not saved because another operation failed".to_string()), Vec::new()),
+            ApiError::NotFound | ApiError::Http(_) => (None, Vec::new()),
+        };
+
+        Problem {
+            problem_type: "about:blank".to_string(),
+            title: status.reason().unwrap_or("Unknown Error").to_string(),
+            status: status.code,
+            code: code.to_string(),
+            detail,
+            errors,
+        }
+    }
+}
+
+impl From<tokio_postgres::Error> for ApiError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        let db_error = e.as_db_error();
+
+        if db_error.map(|e| e.code()) == Some(&SqlState::UNIQUE_VIOLATION) {
+            return match db_error.and_then(|e| e.constraint()) {
+                Some("users_email_key") =>
+                    ApiError::Conflict { code: codes::EMAIL_TAKEN, detail: "Email already in use" },
+                _ => ApiError::Conflict { code: codes::CONFLICT, detail: "Resource already exists" },
+            };
+        }
+
+        ApiError::Db(e)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.status();
+
+        Response::build_from(Json(self.into_problem()).respond_to(req)?)
+            .status(status)
+            .header(ContentType::new("application", "problem+json"))
+            .ok()
+    }
+}
+
+#[catch(default)]
+pub fn default_catcher(status: Status, _req: &Request) -> ApiError {
+    if status == Status::NotFound { ApiError::NotFound } else { ApiError::Http(status) }
+}
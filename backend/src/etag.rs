@@ -0,0 +1,110 @@
+use rocket::http::Status;
+use rocket::request::{ self, FromRequest, Request };
+use rocket::response::{ self, Responder, Response };
+use rocket::serde::json::Json;
+use shared::UserResponse;
+
+use crate::error::ApiError;
+
+/// The entity tag of a user at `version`.
+pub fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+/// A user sent with its version as the `ETag` header.
+pub struct Tagged(pub UserResponse);
+
+impl<'r> Responder<'r, 'static> for Tagged {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let etag = etag(self.0.version);
+
+        Response::build_from(Json(self.0).respond_to(req)?)
+            .raw_header("ETag", etag)
+            .ok()
+    }
+}
+
+/// The `If-Match` header of a write. As a guard it rejects requests without
+/// one with 428 Precondition Required, so that no client overwrites a change
+/// it has not seen by leaving the header out.
+pub enum IfMatch {
+    Any,
+    Versions(Vec<i32>),
+}
+
+impl IfMatch {
+    /// Fails with 412 Precondition Failed unless `version` is one of the
+    /// versions the client named.
+    pub fn check(&self, version: i32) -> Result<(), ApiError> {
+        match self {
+            IfMatch::Any => Ok(()),
+            IfMatch::Versions(versions) if versions.contains(&version) => Ok(()),
+            IfMatch::Versions(_) => Err(ApiError::PreconditionFailed),
+        }
+    }
+
+    /// Weak tags never match, as If-Match compares strongly; neither do tags
+    /// this server did not hand out.
+    fn parse(header: &str) -> IfMatch {
+        if header.trim() == "*" {
+            return IfMatch::Any;
+        }
+
+        let versions = header
+            .split(',')
+            .filter_map(|tag| tag.trim().strip_prefix('"')?.strip_suffix('"')?.parse().ok())
+            .collect();
+        IfMatch::Versions(versions)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match req.headers().get_one("If-Match") {
+            Some(header) => request::Outcome::Success(IfMatch::parse(header)),
+            None => request::Outcome::Error((Status::PreconditionRequired, ())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The versions `header` names, or `None` for `*`.
+    fn versions(header: &str) -> Option<Vec<i32>> {
+        match IfMatch::parse(header) {
+            IfMatch::Any => None,
+            IfMatch::Versions(versions) => Some(versions),
+        }
+    }
+
+    #[test]
+    fn parses_tags_and_wildcard() {
+        assert_eq!(versions("*"), None);
+        assert_eq!(versions(" * "), None);
+        assert_eq!(versions(&etag(3)), Some(vec![3]));
+        assert_eq!(versions("\"1\", \"2\",\"3\""), Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn ignores_weak_and_foreign_tags() {
+        assert_eq!(versions("W/\"1\""), Some(vec![]));
+        assert_eq!(versions("1"), Some(vec![]));
+        assert_eq!(versions("\"abc\", \"2\""), Some(vec![2]));
+        assert_eq!(versions("\"\""), Some(vec![]));
+        assert_eq!(versions(""), Some(vec![]));
+        assert_eq!(versions("*, \"1\""), Some(vec![1]));
+    }
+
+    #[test]
+    fn check_compares_versions() {
+        assert!(IfMatch::Any.check(7).is_ok());
+        assert!(IfMatch::parse("\"6\", \"7\"").check(7).is_ok());
+        assert!(matches!(IfMatch::parse("\"6\"").check(7), Err(ApiError::PreconditionFailed)));
+        assert!(matches!(IfMatch::parse("W/\"7\"").check(7), Err(ApiError::PreconditionFailed)));
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::{ HashMap, HashSet };
+use std::pin::pin;
+
+use rocket::Route;
+use rocket::form::Form;
+use rocket::fs::TempFile;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::tokio::io::AsyncReadExt;
+use shared::{ CreateUser, FieldError, ImportReport, ImportRow, ImportStatus, UserResponse, codes, validation };
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::types::Type;
+use utoipa::ToSchema;
+
+use crate::audit::{ self, Actor, AuditContext };
+use crate::auth::Editor;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::users::{ USER_COLUMNS, user_from_row };
+
+pub fn routes() -> Vec<Route> {
+    routes![import_users, import_users_media_type]
+}
+
+/// The multipart form of `POST /api/users/import`, sent by the client from
+/// [`shared::ImportOptions`].
+#[derive(FromForm, ToSchema)]
+pub struct ImportForm<'r> {
+    /// UTF-8 CSV with a header row, at most the `file` limit.
+    #[schema(value_type = String, format = Binary)]
+    file: TempFile<'r>,
+    /// Header of the column holding names; `name` when left out.
+    name_column: Option<String>,
+    /// Header of the column holding emails; `email` when left out.
+    email_column: Option<String>,
+    /// Checks the file and reports what would change without saving it.
+    #[schema(required = false, default = false)]
+    dry_run: bool,
+}
+
+fn file_error(code: &str, message: impl Into<String>) -> ApiError {
+    ApiError::Validation(vec![FieldError::new("file", code, message)])
+}
+
+/// Finds a column by its header, ignoring case and surrounding spaces.
+fn find_column(headers: &csv::StringRecord, field: &str, column: &str) -> Result<usize, ApiError> {
+    headers
+        .iter()
+        .position(|header| header.trim().eq_ignore_ascii_case(column))
+        .ok_or_else(|| {
+            let headers: Vec<&str> = headers.iter().map(str::trim).collect();
+            ApiError::Validation(
+                vec![
+                    FieldError::new(
+                        field,
+                        "unknown_column",
+                        format!("The file has no column \"{}\"; its columns are: {}", column, headers.join(", "))
+                    )
+                ]
+            )
+        })
+}
+
+/// Finds the line of the file at a byte offset. The reader numbers lines
+/// without the blank ones it skips, which would not match the file as users
+/// see it in an editor.
+struct Lines<'a> {
+    csv: &'a [u8],
+    offset: usize,
+    line: u64,
+}
+
+impl<'a> Lines<'a> {
+    fn new(csv: &'a [u8]) -> Self {
+        Lines { csv, offset: 0, line: 1 }
+    }
+
+    /// Offsets must not go backwards, so that the file is scanned once. A
+    /// record's position is before the blank lines skipped to reach it.
+    fn at(&mut self, position: Option<&csv::Position>) -> u64 {
+        let mut offset = position.map_or(0, |position| position.byte() as usize).min(self.csv.len());
+        while matches!(self.csv.get(offset), Some(b'\r' | b'\n')) {
+            offset += 1;
+        }
+        self.line += self.csv[self.offset..offset].iter().filter(|&&byte| byte == b'\n').count() as u64;
+        self.offset = offset;
+        self.line
+    }
+}
+
+/// Reads the rows of `csv` and validates each like `POST /api/users` would.
+/// Only a file that cannot be read as a whole is an error; a bad row is
+/// reported as `invalid` alongside the others.
+fn read_rows(csv: &[u8], name_column: &str, email_column: &str) -> Result<Vec<ImportRow>, ApiError> {
+    // Spreadsheets leave cells of short rows out rather than empty.
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(csv);
+    let headers = reader.headers().map_err(|_| file_error("invalid", "The header row is not valid UTF-8"))?.clone();
+    if headers.iter().all(|header| header.trim().is_empty()) {
+        return Err(file_error("required", "The file is empty"));
+    }
+    let name_index = find_column(&headers, "name_column", name_column)?;
+    let email_index = find_column(&headers, "email_column", email_column)?;
+
+    let mut lines = Lines::new(csv);
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| {
+            file_error("invalid", format!("Line {} is not valid UTF-8", lines.at(e.position())))
+        })?;
+        // Spreadsheets also tend to export a few rows of empty cells.
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        if rows.len() == validation::IMPORT_MAX_ROWS {
+            return Err(
+                file_error("too_many", format!("At most {} rows can be imported at once", validation::IMPORT_MAX_ROWS))
+            );
+        }
+
+        let line = lines.at(record.position());
+        let name = record.get(name_index).unwrap_or_default().to_string();
+        let email = record.get(email_index).unwrap_or_default().to_string();
+        rows.push(match (CreateUser { name: name.clone(), email: email.clone() }).validated() {
+            Ok(user) => ImportRow {
+                line,
+                name: user.name,
+                email: user.email,
+                status: ImportStatus::Create,
+                errors: Vec::new(),
+                user: None,
+            },
+            Err(errors) => ImportRow { line, name, email, status: ImportStatus::Invalid, errors, user: None },
+        });
+    }
+
+    Ok(rows)
+}
+
+fn mark_duplicate(row: &mut ImportRow, error: FieldError) {
+    row.status = ImportStatus::Duplicate;
+    row.errors.push(error);
+}
+
+fn email_taken() -> FieldError {
+    FieldError::new("email", codes::EMAIL_TAKEN, "Email already in use")
+}
+
+/// Marks rows whose email an active user already has, and rows repeating the
+/// email of an earlier row, as duplicates; emails compare case-insensitively,
+/// as in the unique index.
+async fn mark_duplicates(conn: &Db, rows: &mut [ImportRow]) -> Result<(), ApiError> {
+    let emails: Vec<String> = rows
+        .iter()
+        .filter(|row| row.status == ImportStatus::Create)
+        .map(|row| row.email.to_lowercase())
+        .collect();
+    let taken: HashSet<String> = conn
+        .query(
+            "SELECT lower(btrim(email)) FROM users WHERE deleted_at IS NULL AND lower(btrim(email)) = ANY($1)",
+            &[&emails]
+        ).await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    let mut first_lines = HashMap::new();
+    for row in rows.iter_mut().filter(|row| row.status == ImportStatus::Create) {
+        let email = row.email.to_lowercase();
+        if taken.contains(&email) {
+            mark_duplicate(row, email_taken());
+        } else if let Some(line) = first_lines.get(&email) {
+            mark_duplicate(row, FieldError::new("email", "duplicate", format!("Same email as line {}", line)));
+        } else {
+            first_lines.insert(email, row.line);
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the users of the `create` rows with `COPY` into a temporary table
+/// and one `INSERT` from it, and returns them by lowercased email. A user
+/// created with the same email since [`mark_duplicates`] makes its row be
+/// left out rather than failing the import.
+async fn insert_rows(
+    conn: &mut Db,
+    context: &AuditContext,
+    actor: Actor,
+    rows: &[ImportRow]
+) -> Result<HashMap<String, UserResponse>, ApiError> {
+    let tx = conn.transaction().await?;
+    tx.batch_execute(
+        "CREATE TEMPORARY TABLE user_import (line BIGINT NOT NULL, name TEXT NOT NULL, email TEXT NOT NULL)
+        ON COMMIT DROP"
+    ).await?;
+
+    let sink = tx.copy_in("COPY user_import (line, name, email) FROM STDIN (FORMAT binary)").await?;
+    let mut writer = pin!(BinaryCopyInWriter::new(sink, &[Type::INT8, Type::TEXT, Type::TEXT]));
+    for row in rows.iter().filter(|row| row.status == ImportStatus::Create) {
+        writer.as_mut().write(&[&(row.line as i64), &row.name, &row.email]).await?;
+    }
+    writer.finish().await?;
+
+    // Ordered by line so that ids follow the file.
+    let users: Vec<UserResponse> = tx
+        .query(
+            &format!(
+                "INSERT INTO users (name, email)
+                SELECT name, email FROM user_import ORDER BY line
+                ON CONFLICT (lower(btrim(email))) WHERE deleted_at IS NULL DO NOTHING
+                RETURNING {}",
+                USER_COLUMNS
+            ),
+            &[]
+        ).await?
+        .iter()
+        .map(user_from_row)
+        .collect();
+    audit::record_creates(&tx, context, actor, &users).await?;
+    tx.commit().await?;
+
+    Ok(users.into_iter().map(|user| (user.email.to_lowercase(), user)).collect())
+}
+
+#[utoipa::path(
+    tag = "users",
+    request_body(content = ImportForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "A row per data row of the file, with the users created unless `dry_run` was set", body = ImportReport),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only editors, admins and keys with users:write can create users", body = Problem, content_type = "application/problem+json"),
+        (status = 413, description = "File over the `file` limit", body = Problem, content_type = "application/problem+json"),
+        (status = 415, description = "Not sent as multipart/form-data", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Unreadable file, unknown column or too many rows", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []), ("api_key" = []))
+)]
+#[post("/api/users/import", format = "multipart/form-data", data = "<form>")]
+async fn import_users(
+    caller: Editor,
+    mut conn: Db,
+    context: AuditContext,
+    form: Form<ImportForm<'_>>
+) -> Result<Json<ImportReport>, ApiError> {
+    let column = |column: &Option<String>, default: &str| {
+        column.as_deref().map(str::trim).filter(|column| !column.is_empty()).unwrap_or(default).to_string()
+    };
+    let name_column = column(&form.name_column, "name");
+    let email_column = column(&form.email_column, "email");
+
+    let mut csv = Vec::new();
+    form.file
+        .open().await
+        .map_err(|_| ApiError::Http(Status::InternalServerError))?
+        .read_to_end(&mut csv).await
+        .map_err(|_| ApiError::Http(Status::InternalServerError))?;
+
+    let mut rows = read_rows(&csv, &name_column, &email_column)?;
+    mark_duplicates(&conn, &mut rows).await?;
+
+    let mut created = 0;
+    if !form.dry_run && rows.iter().any(|row| row.status == ImportStatus::Create) {
+        let mut users = insert_rows(&mut conn, &context, Actor::from(&*caller), &rows).await?;
+        for row in rows.iter_mut().filter(|row| row.status == ImportStatus::Create) {
+            match users.remove(&row.email.to_lowercase()) {
+                Some(user) => {
+                    row.user = Some(user);
+                    created += 1;
+                }
+                None => mark_duplicate(row, email_taken()),
+            }
+        }
+    }
+
+    Ok(Json(ImportReport { dry_run: form.dry_run, name_column, email_column, created, rows }))
+}
+
+/// Catches imports in any other format, which would otherwise look like a
+/// missing route.
+#[post("/api/users/import", rank = 2)]
+fn import_users_media_type() -> ApiError {
+    ApiError::Http(Status::UnsupportedMediaType)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(csv: &str) -> Vec<u64> {
+        read_rows(csv.as_bytes(), "name", "email").unwrap().iter().map(|row| row.line).collect()
+    }
+
+    #[test]
+    fn lines_count_blank_lines() {
+        assert_eq!(lines("name,email\n\n\nAda,ada@example.com\n\nBob,bob@example.com\n"), vec![4, 6]);
+        assert_eq!(lines("\r\nname,email\r\n\r\nAda,ada@example.com\r\n"), vec![4]);
+    }
+
+    #[test]
+    fn invalid_utf8_reports_its_line() {
+        let Err(ApiError::Validation(errors)) = read_rows(b"name,email\n\nAda,\xff\n", "name", "email") else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(errors[0].message, "Line 3 is not valid UTF-8");
+    }
+}
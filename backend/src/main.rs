@@ -1,109 +1,101 @@
 #[macro_use]
 extern crate rocket;
 
-use rocket::serde::{ Deserialize, Serialize, json::Json };
-use rocket::{ State, response::status::Custom, http::Status };
-use tokio_postgres::{ Client, NoTls };
-use rocket_cors::{ CorsOptions, AllowedOrigins };
+mod api_keys;
+mod audit;
+mod auth;
+mod bulk;
+mod cli;
+mod config;
+mod db;
+mod error;
+mod etag;
+mod import;
+mod migrations;
+mod openapi;
+mod pagination;
+mod purge;
+mod request_id;
+mod tokens;
+mod users;
 
-#[derive(Serialize, Deserialize, Clone)]
-struct User {
-    id: Option<i32>,
-    name: String,
-    email: String,
-}
-
-#[post("/api/users", data = "<user>")]
-async fn add_user(
-    conn: &State<Client>,
-    user: Json<User>
-) -> Result<Json<Vec<User>>, Custom<String>> {
-    execute_query(
-        conn,
-        "INSERT INTO users (name, email) VALUES ($1, $2)",
-        &[&user.name, &user.email]
-    ).await?;
-    get_users(conn).await
-}
-
-#[get("/api/users")]
-async fn get_users(conn: &State<Client>) -> Result<Json<Vec<User>>, Custom<String>> {
-    get_users_from_db(conn).await.map(Json)
-}
+use cli::Command;
+use config::AppConfig;
 
-async fn get_users_from_db(client: &Client) -> Result<Vec<User>, Custom<String>> {
-    let users = client
-        .query("SELECT id, name, email FROM users", &[]).await
-        .map_err(|e| Custom(Status::InternalServerError, e.to_string()))?
-        .iter()
-        .map(|row| User { id: Some(row.get(0)), name: row.get(1), email: row.get(2) })
-        .collect::<Vec<User>>();
+#[rocket::main]
+async fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let command = Command::from_args(&args).unwrap_or_else(|usage| {
+        eprintln!("{}", usage);
+        std::process::exit(2);
+    });
 
-    Ok(users)
-}
+    let figment = config::figment();
+    let app_config = AppConfig::from_figment(&figment).unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        std::process::exit(1);
+    });
 
-#[put("/api/users/<id>", data = "<user>")]
-async fn update_user(
-    conn: &State<Client>,
-    id: i32,
-    user: Json<User>
-) -> Result<Json<Vec<User>>, Custom<String>> {
-    execute_query(
-        conn,
-        "UPDATE users SET name = $1, email = $2 WHERE id = $3",
-        &[&user.name, &user.email, &id]
-    ).await?;
-    get_users(conn).await
-}
+    let pool = db::init(&app_config.database).await.expect("Failed to connect to Postgres");
+    let mut client = pool.get().await.expect("Failed to connect to Postgres");
 
-#[delete("/api/users/<id>")]
-async fn delete_user(conn: &State<Client>, id: i32) -> Result<Status, Custom<String>> {
-    execute_query(conn, "DELETE FROM users WHERE id = $1", &[&id]).await?;
-    Ok(Status::NoContent)
-}
+    if !matches!(command, Command::Serve) {
+        if let Err(e) = command.run(&mut client).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-async fn execute_query(
-    client: &Client,
-    query: &str,
-    params: &[&(dyn tokio_postgres::types::ToSql + Sync)]
-) -> Result<u64, Custom<String>> {
-    client
-        .execute(query, params).await
-        .map_err(|e| Custom(Status::InternalServerError, e.to_string()))
-}
+    let pending = if app_config.database.auto_migrate {
+        migrations::run_pending(&mut client).await.map(|_| Vec::new())
+    } else {
+        migrations::pending(&client).await
+    };
+    match pending {
+        Ok(pending) if pending.is_empty() => {}
+        Ok(pending) => {
+            eprintln!(
+                "{} pending migration(s), starting with {}; run `backend --migrate` first",
+                pending.len(),
+                pending[0].name
+            );
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Migration failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+    drop(client);
 
-#[launch]
-async fn rocket() -> _ {
-    let (client, connection) = tokio_postgres
-        ::connect("host=localhost user=postgres password=postgres dbname=postgres", NoTls).await
-        .expect("Failed to connect to Postgres");
+    let cors = app_config.cors().expect("Validated above");
+    let jwt_keys = app_config.jwt_keys().expect("Validated above");
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("Failed to connect to Postgres: {}", e);
-        }
-    });
+    purge::spawn(pool.clone(), app_config.users);
 
-    //Create the table if it doesn't exist
-    client
-        .execute(
-            "CREATE TABLE IF NOT EXISTS users (
-                id SERIAL PRIMARY KEY,
-                name TEXT NOT NULL,
-                email TEXT NOT NULL
-            )",
-            &[]
-        ).await
-        .expect("Failed to create table");
+    let mut rocket = rocket
+        ::custom(figment)
+        .manage(pool)
+        .manage(app_config.auth)
+        .mount("/", users::routes())
+        .mount("/", bulk::routes())
+        .mount("/", import::routes())
+        .mount("/", auth::routes())
+        .mount("/", api_keys::routes())
+        .mount("/", audit::routes())
+        .mount("/", openapi::routes())
+        .register("/", catchers![error::default_catcher])
+        .attach(request_id::RequestIdFairing)
+        .attach(cors);
+    if let Some(jwt_keys) = jwt_keys {
+        rocket = rocket.manage(jwt_keys).mount("/", tokens::routes());
+    }
 
-    let cors = CorsOptions::default()
-        .allowed_origins(AllowedOrigins::all())
-        .to_cors()
-        .expect("Error while building CORS");
+    let result = rocket.launch().await;
 
-    rocket
-        ::build()
-        .manage(client)
-        .mount("/", routes![add_user, get_users, update_user, delete_user])
-        .attach(cors)
+    if let Err(e) = result {
+        eprintln!("Failed to launch: {}", e);
+        std::process::exit(1);
+    }
 }
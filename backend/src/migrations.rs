@@ -0,0 +1,231 @@
+use std::fmt;
+
+use sha2::{ Digest, Sha256 };
+use tokio_postgres::Client;
+
+/// A schema change embedded from `migrations/<name>.up.sql` and its matching
+/// `.down.sql`. Versions must be strictly increasing.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    up: &'static str,
+    down: &'static str,
+}
+
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!("../migrations/", $name, ".up.sql")),
+            down: include_str!(concat!("../migrations/", $name, ".down.sql")),
+        }
+    };
+}
+
+static MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_create_users"),
+    migration!(2, "0002_unique_user_email"),
+    migration!(3, "0003_user_search"),
+    migration!(4, "0004_user_passwords"),
+    migration!(5, "0005_refresh_tokens"),
+    migration!(6, "0006_user_roles"),
+    migration!(7, "0007_api_keys"),
+    migration!(8, "0008_audit_events"),
+    migration!(9, "0009_soft_delete"),
+    migration!(10, "0010_user_version"),
+    migration!(11, "0011_user_timestamps"),
+];
+
+// Arbitrary key for pg_advisory_lock so that two instances starting at the
+// same time don't apply the same migration twice.
+const LOCK_KEY: i64 = 0x7573_6572_735f_6d67;
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+pub enum MigrationStatus<'a> {
+    Applied(AppliedMigration),
+    Pending(&'a Migration),
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Db(tokio_postgres::Error),
+    ChecksumMismatch {
+        version: i64,
+        name: String,
+    },
+    Unknown {
+        version: i64,
+        name: String,
+    },
+}
+
+impl From<tokio_postgres::Error> for MigrationError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        MigrationError::Db(e)
+    }
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Db(e) => write!(f, "{}", e),
+            MigrationError::ChecksumMismatch { version, name } =>
+                write!(f, "migration {} ({}) was modified after it was applied", version, name),
+            MigrationError::Unknown { version, name } =>
+                write!(
+                    f,
+                    "migration {} ({}) is applied but unknown to this binary; is it out of date?",
+                    version,
+                    name
+                ),
+        }
+    }
+}
+
+async fn ensure_table(client: &Client) -> Result<(), MigrationError> {
+    client.batch_execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )"
+    ).await?;
+    Ok(())
+}
+
+/// Applied migrations in version order, after checking that each one still
+/// matches the embedded SQL.
+async fn applied(client: &Client) -> Result<Vec<AppliedMigration>, MigrationError> {
+    ensure_table(client).await?;
+
+    let applied = client
+        .query(
+            "SELECT version, name, checksum, applied_at::text FROM schema_migrations ORDER BY version",
+            &[]
+        ).await?
+        .iter()
+        .map(|row| AppliedMigration {
+            version: row.get(0),
+            name: row.get(1),
+            checksum: row.get(2),
+            applied_at: row.get(3),
+        })
+        .collect::<Vec<_>>();
+
+    for migration in &applied {
+        match MIGRATIONS.iter().find(|m| m.version == migration.version) {
+            Some(known) if known.checksum() == migration.checksum => {}
+            Some(_) => {
+                return Err(MigrationError::ChecksumMismatch {
+                    version: migration.version,
+                    name: migration.name.clone(),
+                });
+            }
+            None => {
+                return Err(MigrationError::Unknown {
+                    version: migration.version,
+                    name: migration.name.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(applied)
+}
+
+pub async fn status(client: &Client) -> Result<Vec<MigrationStatus<'static>>, MigrationError> {
+    let mut applied = applied(client).await?.into_iter().peekable();
+
+    Ok(
+        MIGRATIONS.iter()
+            .map(|migration| {
+                match applied.next_if(|a| a.version == migration.version) {
+                    Some(a) => MigrationStatus::Applied(a),
+                    None => MigrationStatus::Pending(migration),
+                }
+            })
+            .collect()
+    )
+}
+
+pub async fn pending(client: &Client) -> Result<Vec<&'static Migration>, MigrationError> {
+    let applied = applied(client).await?;
+
+    Ok(
+        MIGRATIONS.iter()
+            .filter(|m| !applied.iter().any(|a| a.version == m.version))
+            .collect()
+    )
+}
+
+/// Applies every pending migration, each in its own transaction, and returns
+/// the ones that ran.
+pub async fn run_pending(client: &mut Client) -> Result<Vec<&'static Migration>, MigrationError> {
+    client.execute("SELECT pg_advisory_lock($1)", &[&LOCK_KEY]).await?;
+    let result = apply_pending(client).await;
+    client.execute("SELECT pg_advisory_unlock($1)", &[&LOCK_KEY]).await?;
+    result
+}
+
+async fn apply_pending(client: &mut Client) -> Result<Vec<&'static Migration>, MigrationError> {
+    let pending = pending(client).await?;
+
+    for migration in &pending {
+        let tx = client.transaction().await?;
+        tx.batch_execute(migration.up).await?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)",
+            &[&migration.version, &migration.name, &migration.checksum()]
+        ).await?;
+        tx.commit().await?;
+    }
+
+    Ok(pending)
+}
+
+/// Reverts the `steps` most recently applied migrations, newest first.
+pub async fn revert(
+    client: &mut Client,
+    steps: usize
+) -> Result<Vec<&'static Migration>, MigrationError> {
+    client.execute("SELECT pg_advisory_lock($1)", &[&LOCK_KEY]).await?;
+    let result = revert_applied(client, steps).await;
+    client.execute("SELECT pg_advisory_unlock($1)", &[&LOCK_KEY]).await?;
+    result
+}
+
+async fn revert_applied(
+    client: &mut Client,
+    steps: usize
+) -> Result<Vec<&'static Migration>, MigrationError> {
+    let applied = applied(client).await?;
+    let mut reverted = Vec::new();
+
+    for applied in applied.iter().rev().take(steps) {
+        let migration = MIGRATIONS.iter()
+            .find(|m| m.version == applied.version)
+            .expect("Checked by applied()");
+
+        let tx = client.transaction().await?;
+        tx.batch_execute(migration.down).await?;
+        tx.execute("DELETE FROM schema_migrations WHERE version = $1", &[&migration.version]).await?;
+        tx.commit().await?;
+        reverted.push(migration);
+    }
+
+    Ok(reverted)
+}
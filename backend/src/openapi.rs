@@ -0,0 +1,148 @@
+use rocket::Route;
+use rocket::serde::json::Json;
+use shared::{
+    ActorKind,
+    ApiKeyResponse,
+    AuditAction,
+    AuditEvent,
+    AuditPage,
+    BulkMode,
+    BulkOperation,
+    BulkRequest,
+    BulkResponse,
+    BulkResult,
+    CreateApiKey,
+    CreateUser,
+    CreatedApiKey,
+    FieldError,
+    ImportReport,
+    ImportRow,
+    ImportStatus,
+    Login,
+    Problem,
+    RevokeToken,
+    Role,
+    Scope,
+    SearchHit,
+    SetRole,
+    TokenRequest,
+    TokenResponse,
+    UpdateUser,
+    UserPatch,
+    UserPage,
+    UserResponse,
+};
+use utoipa::{ Modify, OpenApi };
+use utoipa::openapi::OpenApi as OpenApiDocument;
+use utoipa::openapi::security::{ ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme };
+use utoipa_rapidoc::RapiDoc;
+
+use crate::{ api_keys, audit, auth, bulk, import, tokens, users };
+
+/// OpenAPI description of the `/api` endpoints, assembled from the
+/// `#[utoipa::path]` annotations on the handlers.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "User Management API"),
+    paths(
+        users::add_user,
+        users::get_users,
+        users::search_users,
+        users::get_user,
+        users::update_user,
+        users::patch_user,
+        users::set_role,
+        users::delete_user,
+        users::restore_user,
+        bulk::bulk_users,
+        import::import_users,
+        auth::login,
+        auth::logout,
+        auth::me,
+        tokens::token,
+        tokens::revoke,
+        api_keys::create_api_key,
+        api_keys::get_api_keys,
+        api_keys::revoke_api_key,
+        audit::get_audit_events
+    ),
+    components(
+        schemas(
+            CreateUser,
+            UpdateUser,
+            UserPatch,
+            UserResponse,
+            Role,
+            SetRole,
+            UserPage,
+            SearchHit,
+            BulkMode,
+            BulkOperation,
+            BulkRequest,
+            BulkResult,
+            BulkResponse,
+            import::ImportForm,
+            ImportStatus,
+            ImportRow,
+            ImportReport,
+            Login,
+            TokenRequest,
+            TokenResponse,
+            RevokeToken,
+            Scope,
+            CreateApiKey,
+            ApiKeyResponse,
+            CreatedApiKey,
+            ActorKind,
+            AuditAction,
+            AuditEvent,
+            AuditPage,
+            Problem,
+            FieldError
+        )
+    ),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "users", description = "Create, list, search, update and delete users; viewers read, editors write, admins delete"),
+        (name = "auth", description = "Session cookies for the web app, bearer tokens for services"),
+        (name = "keys", description = "API keys for services, managed by admins"),
+        (name = "audit", description = "Who changed which user, when and from where")
+    )
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "session",
+                SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("session")))
+            );
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key")))
+            );
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(
+                    HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()
+                )
+            );
+        }
+    }
+}
+
+/// Serves the document at `/api/openapi.json` and a RapiDoc UI for it at
+/// `/api/docs`.
+pub fn routes() -> Vec<Route> {
+    let mut routes = routes![openapi_json];
+    routes.extend(Vec::<Route>::from(RapiDoc::new("/api/openapi.json").path("/api/docs")));
+    routes
+}
+
+#[get("/api/openapi.json")]
+fn openapi_json() -> Json<OpenApiDocument> {
+    Json(ApiDoc::openapi())
+}
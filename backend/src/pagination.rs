@@ -0,0 +1,176 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rocket::serde::{ Deserialize, Serialize, json };
+use shared::{ FieldError, Page };
+use utoipa::IntoParams;
+
+use crate::error::ApiError;
+
+pub const DEFAULT_LIMIT: i64 = 20;
+pub const MAX_LIMIT: i64 = 100;
+
+/// Position of a row in a listing. Clients only ever see it as an opaque
+/// string so the encoding can change without breaking them.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Cursor {
+    /// The `sort` the cursor was issued for; it is meaningless under another.
+    pub sort: String,
+    /// Value of the sort column, absent when sorting by `id` alone.
+    pub key: Option<String>,
+    pub id: i64,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(json::to_string(self).expect("Cursor is serializable"))
+    }
+
+    pub fn decode(field: &'static str, value: &str) -> Result<Cursor, ApiError> {
+        URL_SAFE_NO_PAD.decode(value)
+            .ok()
+            .and_then(|bytes| json::from_slice(&bytes).ok())
+            .ok_or_else(|| {
+                ApiError::Validation(vec![FieldError::new(field, "invalid", "Cursor is malformed")])
+            })
+    }
+}
+
+/// Which way a keyset query walks from its cursor.
+pub enum Direction {
+    First,
+    After(Cursor),
+    Before(Cursor),
+}
+
+#[derive(FromForm, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    /// Between 1 and 100, defaults to 20.
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page.
+    pub after: Option<String>,
+    /// `prev_cursor` of the previous page.
+    pub before: Option<String>,
+}
+
+impl PageParams {
+    pub fn limit(&self) -> Result<i64, ApiError> {
+        match self.limit {
+            None => Ok(DEFAULT_LIMIT),
+            Some(limit) if (1..=MAX_LIMIT).contains(&limit) => Ok(limit),
+            Some(_) =>
+                Err(
+                    ApiError::Validation(
+                        vec![
+                            FieldError::new(
+                                "limit",
+                                "out_of_range",
+                                format!("Limit must be between 1 and {}", MAX_LIMIT)
+                            )
+                        ]
+                    )
+                ),
+        }
+    }
+
+    pub fn direction(&self) -> Result<Direction, ApiError> {
+        match (&self.after, &self.before) {
+            (None, None) => Ok(Direction::First),
+            (Some(after), None) => Ok(Direction::After(Cursor::decode("after", after)?)),
+            (None, Some(before)) => Ok(Direction::Before(Cursor::decode("before", before)?)),
+            (Some(_), Some(_)) =>
+                Err(
+                    ApiError::Validation(
+                        vec![
+                            FieldError::new(
+                                "before",
+                                "conflicting",
+                                "Only one of after and before can be given"
+                            )
+                        ]
+                    )
+                ),
+        }
+    }
+}
+
+/// Builds a page from up to `limit + 1` rows fetched in walking order; the
+/// extra row only signals that more exist in that direction.
+pub fn page_from_rows<T>(
+    mut items: Vec<T>,
+    limit: i64,
+    direction: &Direction,
+    total: i64,
+    cursor: impl Fn(&T) -> Cursor
+) -> Page<T> {
+    let has_more = items.len() as i64 > limit;
+    items.truncate(limit as usize);
+    if let Direction::Before(_) = direction {
+        items.reverse();
+    }
+
+    let (has_next, has_prev) = match direction {
+        Direction::First => (has_more, false),
+        Direction::After(_) => (has_more, true),
+        Direction::Before(_) => (true, has_more),
+    };
+
+    Page {
+        next_cursor: items
+            .last()
+            .filter(|_| has_next)
+            .map(|item| cursor(item).encode()),
+        prev_cursor: items
+            .first()
+            .filter(|_| has_prev)
+            .map(|item| cursor(item).encode()),
+        items,
+        total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_error(value: &str) -> Vec<FieldError> {
+        match Cursor::decode("after", value) {
+            Err(ApiError::Validation(errors)) => errors,
+            _ => panic!("{} should not decode", value),
+        }
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        for key in [None, Some("1700000000123456".to_string()), Some("ada@example.com".to_string())] {
+            let cursor = Cursor { sort: "-created_at".to_string(), key: key.clone(), id: 42 };
+            let encoded = cursor.encode();
+            assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'), "{}", encoded);
+
+            let decoded = Cursor::decode("after", &encoded).ok().unwrap();
+            assert_eq!(decoded.sort, "-created_at");
+            assert_eq!(decoded.key, key);
+            assert_eq!(decoded.id, 42);
+        }
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        let not_json = URL_SAFE_NO_PAD.encode("not json");
+        let wrong_shape = URL_SAFE_NO_PAD.encode(r#"{"sort":"id"}"#);
+        for value in ["", "!!!", not_json.as_str(), wrong_shape.as_str()] {
+            let errors = decode_error(value);
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].field, "after");
+            assert_eq!(errors[0].code, "invalid");
+        }
+    }
+
+    #[test]
+    fn after_and_before_conflict() {
+        let cursor = Cursor { sort: "id".to_string(), key: None, id: 1 }.encode();
+        let params = PageParams { limit: None, after: Some(cursor.clone()), before: Some(cursor) };
+        assert!(matches!(params.direction(), Err(ApiError::Validation(errors)) if errors[0].code == "conflicting"));
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use rocket::tokio;
+use tokio_postgres::Client;
+
+use crate::config::UsersConfig;
+use crate::db::Pool;
+use crate::request_id;
+
+/// Permanently deletes users deleted more than `retention` seconds ago, with a
+/// `user.purge` audit event for each, and returns how many were purged. The
+/// event's `before` has the shape `audit::record` gives it.
+pub async fn purge_deleted_users(client: &Client, retention: u64) -> Result<u64, tokio_postgres::Error> {
+    // One statement, so a purge is never left without its audit event.
+    client.execute(
+        "WITH purged AS (
+            DELETE FROM users WHERE deleted_at < now() - make_interval(secs => $1)
+            RETURNING id, name, email, role, version, created_at, updated_at, deleted_at
+        )
+        INSERT INTO audit_events (actor_kind, actor_id, action, target_id, before, request_id)
+        SELECT 'system', 0, 'user.purge', id,
+            jsonb_build_object(
+                'id', id, 'name', name, 'email', email, 'role', role, 'version', version,
+                'created_at', floor(extract(epoch FROM created_at))::bigint,
+                'updated_at', floor(extract(epoch FROM updated_at))::bigint,
+                'deleted_at', floor(extract(epoch FROM deleted_at))::bigint
+            ),
+            $2
+        FROM purged",
+        &[&(retention as f64), &request_id::generate()]
+    ).await
+}
+
+/// Purges deleted users every `purge_interval` seconds until shutdown.
+pub fn spawn(pool: Pool, config: UsersConfig) {
+    if config.purge_interval == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.purge_interval));
+        loop {
+            interval.tick().await;
+            let conn = match pool.get().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!("Skipping purge of deleted users: {}", e);
+                    continue;
+                }
+            };
+            match purge_deleted_users(&conn, config.deleted_retention).await {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {} deleted user(s)", purged),
+                Err(e) => error!("Failed to purge deleted users: {}", e),
+            }
+        }
+    });
+}
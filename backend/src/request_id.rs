@@ -0,0 +1,63 @@
+use std::convert::Infallible;
+
+use argon2::password_hash::rand_core::{ OsRng, RngCore };
+use rocket::{ Data, Request, Response };
+use rocket::fairing::{ Fairing, Info, Kind };
+use rocket::request::{ self, FromRequest };
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+const MAX_LEN: usize = 64;
+
+/// Identifies a request in audit events and the `X-Request-Id` response
+/// header. A well-formed id sent by the client or a proxy is kept, so one id
+/// can follow a request across services; anything else is replaced.
+#[derive(Clone)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    fn of<'r>(req: &'r Request<'_>) -> &'r RequestId {
+        req.local_cache(|| {
+            let given = req.headers()
+                .get_one(REQUEST_ID_HEADER)
+                .filter(|id| {
+                    !id.is_empty() &&
+                        id.len() <= MAX_LEN &&
+                        id.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+                });
+            RequestId(given.map_or_else(generate, str::to_string))
+        })
+    }
+}
+
+pub fn generate() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub struct RequestIdFairing;
+
+#[rocket::async_trait]
+impl Fairing for RequestIdFairing {
+    fn info(&self) -> Info {
+        Info { name: "Request id", kind: Kind::Request | Kind::Response }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _: &mut Data<'_>) {
+        RequestId::of(req);
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        res.set_raw_header(REQUEST_ID_HEADER, RequestId::of(req).0.clone());
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        request::Outcome::Success(RequestId::of(req).clone())
+    }
+}
@@ -0,0 +1,249 @@
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use argon2::password_hash::rand_core::{ OsRng, RngCore };
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::{ Algorithm, DecodingKey, EncodingKey, Header, Validation };
+use rocket::{ Route, State };
+use rocket::http::Status;
+use rocket::serde::{ Deserialize, Serialize };
+use rocket::serde::json::Json;
+use sha2::{ Digest, Sha256 };
+use shared::{ RevokeToken, Role, TokenRequest, TokenResponse };
+use tokio_postgres::{ Client, GenericClient };
+
+use crate::auth;
+use crate::config::{ ConfigError, JwtAlgorithm, JwtConfig };
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::users;
+
+pub fn routes() -> Vec<Route> {
+    routes![token, revoke]
+}
+
+/// Keys and rules for the access tokens this server issues and accepts.
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    header: Header,
+    validation: Validation,
+    issuer: String,
+    audience: String,
+    access_ttl: u64,
+    refresh_ttl: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Claims {
+    /// The user id.
+    sub: String,
+    /// The user's role when the token was issued.
+    role: Role,
+    iss: String,
+    aud: String,
+    iat: u64,
+    exp: u64,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &JwtConfig) -> Result<JwtKeys, ConfigError> {
+        let (algorithm, encoding, decoding) = match config.algorithm {
+            JwtAlgorithm::HS256 => {
+                let secret = config.secret
+                    .as_deref()
+                    .filter(|secret| secret.len() >= 32)
+                    .ok_or_else(|| ConfigError::Jwt("HS256 needs a secret of at least 32 bytes".into()))?;
+                (
+                    Algorithm::HS256,
+                    EncodingKey::from_secret(secret.as_bytes()),
+                    DecodingKey::from_secret(secret.as_bytes()),
+                )
+            }
+            JwtAlgorithm::RS256 => {
+                let private_key = read_key(config.private_key.as_deref(), "private_key")?;
+                let public_key = read_key(config.public_key.as_deref(), "public_key")?;
+                (
+                    Algorithm::RS256,
+                    EncodingKey::from_rsa_pem(&private_key).map_err(|e| {
+                        ConfigError::Jwt(format!("private_key is not an RSA PEM key: {}", e))
+                    })?,
+                    DecodingKey::from_rsa_pem(&public_key).map_err(|e| {
+                        ConfigError::Jwt(format!("public_key is not an RSA PEM key: {}", e))
+                    })?,
+                )
+            }
+        };
+        if config.access_ttl == 0 || config.refresh_ttl == 0 {
+            return Err(ConfigError::Jwt("access_ttl and refresh_ttl must be at least 1 second".into()));
+        }
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_audience(&[&config.audience]);
+        validation.set_issuer(&[&config.issuer]);
+        validation.set_required_spec_claims(&["exp", "sub", "aud", "iss"]);
+
+        Ok(JwtKeys {
+            encoding,
+            decoding,
+            header: Header::new(algorithm),
+            validation,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            access_ttl: config.access_ttl,
+            refresh_ttl: config.refresh_ttl,
+        })
+    }
+
+    fn issue(&self, user_id: i32, role: Role) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Clock is after 1970").as_secs();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            role,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            iat: now,
+            exp: now + self.access_ttl,
+        };
+
+        jsonwebtoken::encode(&self.header, &claims, &self.encoding).expect("Keys are validated at startup")
+    }
+
+    /// The user id and role of a valid access token. Signature, algorithm,
+    /// expiry, issuer and audience are all checked.
+    pub fn verify(&self, token: &str) -> Option<(i32, Role)> {
+        let claims = jsonwebtoken::decode::<Claims>(token, &self.decoding, &self.validation).ok()?.claims;
+        Some((claims.sub.parse().ok()?, claims.role))
+    }
+}
+
+fn read_key(path: Option<&str>, name: &str) -> Result<Vec<u8>, ConfigError> {
+    let path = path.ok_or_else(|| ConfigError::Jwt(format!("RS256 needs {}", name)))?;
+    std::fs::read(path).map_err(|e| ConfigError::Jwt(format!("cannot read {} {}: {}", name, path, e)))
+}
+
+/// Refresh tokens are random and only their digest is stored, so a database
+/// leak does not leak usable tokens.
+fn refresh_token_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Stores a new refresh token for `user_id`, starting a new family unless one
+/// is given, and returns it.
+async fn store_refresh_token(
+    client: &impl GenericClient,
+    keys: &JwtKeys,
+    user_id: i32,
+    family_id: Option<i64>
+) -> Result<String, ApiError> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+
+    let id: i64 = client.query_one("SELECT nextval('refresh_tokens_id_seq')", &[]).await?.get(0);
+    client.execute(
+        "INSERT INTO refresh_tokens (id, token_hash, user_id, family_id, expires_at)
+        VALUES ($1, $2, $3, $4, now() + make_interval(secs => $5))",
+        &[&id, &refresh_token_hash(&token), &user_id, &family_id.unwrap_or(id), &(keys.refresh_ttl as f64)]
+    ).await?;
+
+    Ok(token)
+}
+
+/// Spends a refresh token and returns its user's id and current role with the
+/// token replacing it.
+async fn rotate_refresh_token(
+    client: &mut Client,
+    keys: &JwtKeys,
+    token: &str
+) -> Result<(i32, Role, String), ApiError> {
+    let tx = client.transaction().await?;
+    let Some(row) = tx.query_opt(
+        "SELECT t.user_id, t.family_id, t.used_at IS NOT NULL AS used,
+            t.revoked_at IS NULL AND t.expires_at > now() AS live, u.role
+        FROM refresh_tokens t JOIN users u ON u.id = t.user_id
+        WHERE t.token_hash = $1 AND u.deleted_at IS NULL FOR UPDATE OF t",
+        &[&refresh_token_hash(token)]
+    ).await? else {
+        return Err(ApiError::InvalidGrant);
+    };
+    let (user_id, family_id): (i32, i64) = (row.get("user_id"), row.get("family_id"));
+
+    if row.get("used") {
+        // Either the client or someone who stole the token already spent it;
+        // there is no telling which, so neither gets to keep the session.
+        tx.execute(
+            "UPDATE refresh_tokens SET revoked_at = now() WHERE family_id = $1 AND revoked_at IS NULL",
+            &[&family_id]
+        ).await?;
+        tx.commit().await?;
+        warn!("Refresh token reused for user {}; revoked its family", user_id);
+        return Err(ApiError::InvalidGrant);
+    }
+    if !row.get::<_, bool>("live") {
+        return Err(ApiError::InvalidGrant);
+    }
+
+    tx.execute(
+        "UPDATE refresh_tokens SET used_at = now() WHERE token_hash = $1",
+        &[&refresh_token_hash(token)]
+    ).await?;
+    let role = users::role_from_row(&row);
+    let token = store_refresh_token(&tx, keys, user_id, Some(family_id)).await?;
+    tx.commit().await?;
+
+    Ok((user_id, role, token))
+}
+
+#[utoipa::path(
+    tag = "auth",
+    request_body = TokenRequest,
+    responses(
+        (status = 200, description = "A new access token and refresh token", body = TokenResponse),
+        (status = 400, description = "The refresh token is unknown, spent, expired or revoked", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Unknown email or wrong password", body = Problem, content_type = "application/problem+json")
+    )
+)]
+#[post("/api/auth/token", data = "<request>")]
+async fn token(
+    mut conn: Db,
+    keys: &State<JwtKeys>,
+    request: Json<TokenRequest>
+) -> Result<Json<TokenResponse>, ApiError> {
+    let (user_id, role, refresh_token) = match request.into_inner() {
+        TokenRequest::Password { email, password } => {
+            let user = auth::authenticate(&conn, &email, password).await?;
+            (user.id, user.role, store_refresh_token(&*conn, keys, user.id, None).await?)
+        }
+        TokenRequest::RefreshToken { refresh_token } =>
+            rotate_refresh_token(&mut conn, keys, &refresh_token).await?,
+    };
+
+    Ok(
+        Json(TokenResponse {
+            access_token: keys.issue(user_id, role),
+            token_type: "Bearer".to_string(),
+            expires_in: keys.access_ttl,
+            refresh_token,
+        })
+    )
+}
+
+#[utoipa::path(
+    tag = "auth",
+    request_body = RevokeToken,
+    responses((status = 204, description = "The token and every token rotated from it are revoked"))
+)]
+#[post("/api/auth/revoke", data = "<request>")]
+async fn revoke(conn: Db, request: Json<RevokeToken>) -> Result<Status, ApiError> {
+    // Unknown tokens are not an error (RFC 7009): the outcome is the same.
+    conn.execute(
+        "UPDATE refresh_tokens SET revoked_at = now()
+        WHERE revoked_at IS NULL
+            AND family_id = (SELECT family_id FROM refresh_tokens WHERE token_hash = $1)",
+        &[&refresh_token_hash(&request.refresh_token)]
+    ).await?;
+
+    Ok(Status::NoContent)
+}
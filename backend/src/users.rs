@@ -0,0 +1,833 @@
+use rocket::Route;
+use rocket::serde::json::Json;
+use rocket::http::Status;
+use rocket::response::status::Created;
+use shared::{
+    AuditAction,
+    CreateUser,
+    FieldError,
+    Page,
+    Role,
+    SearchHit,
+    SetRole,
+    UpdateUser,
+    UserPatch,
+    UserResponse,
+};
+use tokio_postgres::{ Client, GenericClient, Row };
+
+use crate::audit::{ self, Actor, AuditContext };
+use crate::auth::{ Admin, Editor, Viewer };
+use crate::db::{ self, Db, Params, where_clause };
+use crate::error::ApiError;
+use crate::etag::{ IfMatch, Tagged };
+use crate::pagination::{ self, Cursor, Direction, PageParams };
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        add_user,
+        get_users,
+        search_users,
+        get_user,
+        update_user,
+        patch_user,
+        patch_user_media_type,
+        set_role,
+        delete_user,
+        restore_user
+    ]
+}
+
+/// Columns read by [`user_from_row`]. Timestamps are truncated to whole
+/// seconds, which the `created_after` and `created_before` bounds rely on.
+pub const USER_COLUMNS: &str =
+    "id, name, email, role, version,
+    floor(extract(epoch FROM created_at))::bigint AS created_at,
+    floor(extract(epoch FROM updated_at))::bigint AS updated_at,
+    floor(extract(epoch FROM deleted_at))::bigint AS deleted_at";
+
+/// Exact cursor keys of the timestamp sorts, in microseconds: the whole
+/// seconds of the DTO would make paging skip or repeat users sharing a second.
+const TIMESTAMP_KEY_COLUMNS: &str =
+    "(extract(epoch FROM created_at) * 1000000)::bigint AS created_at_key,
+    (extract(epoch FROM updated_at) * 1000000)::bigint AS updated_at_key";
+
+pub fn user_from_row(row: &Row) -> UserResponse {
+    UserResponse {
+        id: row.get("id"),
+        name: row.get("name"),
+        email: row.get("email"),
+        role: role_from_row(row),
+        version: row.get("version"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+pub fn role_from_row(row: &Row) -> Role {
+    Role::parse(row.get("role")).expect("Roles are constrained by users_role_check")
+}
+
+#[utoipa::path(
+    tag = "users",
+    request_body = CreateUser,
+    responses(
+        (status = 201, description = "User created", body = UserResponse),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only editors, admins and keys with users:write can create users", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Email already in use", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid user", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []), ("api_key" = []))
+)]
+#[post("/api/users", data = "<user>")]
+async fn add_user(
+    caller: Editor,
+    mut conn: Db,
+    context: AuditContext,
+    user: Json<CreateUser>
+) -> Result<Created<Json<UserResponse>>, ApiError> {
+    let user = user.into_inner().validated().map_err(ApiError::Validation)?;
+    let tx = conn.transaction().await?;
+    let user = insert_user(&tx, &context, (&*caller).into(), &user).await?;
+    tx.commit().await?;
+
+    Ok(Created::new(format!("/api/users/{}", user.id)).body(Json(user)))
+}
+
+/// Inserts a validated user and records it. Like the other writes below, it
+/// expects to run in a transaction the caller commits.
+pub async fn insert_user(
+    client: &impl GenericClient,
+    context: &AuditContext,
+    actor: Actor,
+    user: &CreateUser
+) -> Result<UserResponse, ApiError> {
+    let row = client.query_one(
+        &format!("INSERT INTO users (name, email) VALUES ($1, $2) RETURNING {}", USER_COLUMNS),
+        &[&user.name, &user.email]
+    ).await?;
+    let user = user_from_row(&row);
+    audit::record(client, context, actor, AuditAction::UserCreate, user.id, None, Some(&user)).await?;
+
+    Ok(user)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortColumn {
+    Id,
+    Name,
+    Email,
+    CreatedAt,
+    UpdatedAt,
+}
+
+/// A whitelisted `sort` value: a column name, optionally prefixed with `-`
+/// for descending order. Ties are always broken by `id` in the same direction.
+#[derive(Clone, Copy)]
+struct Sort {
+    column: SortColumn,
+    descending: bool,
+}
+
+impl Sort {
+    fn parse(value: Option<&str>) -> Result<Sort, ApiError> {
+        let value = value.unwrap_or("id");
+        let (descending, name) = match value.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, value),
+        };
+        let column = match name {
+            "id" => SortColumn::Id,
+            "name" => SortColumn::Name,
+            "email" => SortColumn::Email,
+            "created_at" => SortColumn::CreatedAt,
+            "updated_at" => SortColumn::UpdatedAt,
+            _ => {
+                return Err(
+                    ApiError::Validation(
+                        vec![
+                            FieldError::new(
+                                "sort",
+                                "unsupported",
+                                "Sort must be one of id, name, email, created_at, updated_at, optionally prefixed with -"
+                            )
+                        ]
+                    )
+                );
+            }
+        };
+
+        Ok(Sort { column, descending })
+    }
+
+    fn as_str(&self) -> String {
+        let name = match self.column {
+            SortColumn::Id => "id",
+            SortColumn::Name => "name",
+            SortColumn::Email => "email",
+            SortColumn::CreatedAt => "created_at",
+            SortColumn::UpdatedAt => "updated_at",
+        };
+        if self.descending { format!("-{}", name) } else { name.to_string() }
+    }
+
+    fn column_sql(&self) -> Option<&'static str> {
+        match self.column {
+            SortColumn::Id => None,
+            SortColumn::Name => Some("name"),
+            SortColumn::Email => Some("email"),
+            // The raw columns, so that users_created_at_idx serves the sort.
+            SortColumn::CreatedAt => Some("created_at"),
+            SortColumn::UpdatedAt => Some("updated_at"),
+        }
+    }
+
+    /// Binds a cursor key as the type of the sort column, or returns `None`
+    /// if it is not one.
+    fn push_key(&self, params: &mut Params, key: &str) -> Option<String> {
+        match self.column {
+            SortColumn::CreatedAt | SortColumn::UpdatedAt =>
+                key
+                    .parse::<i64>()
+                    .ok()
+                    .map(|key| format!("timestamptz 'epoch' + {}::bigint * interval '1 microsecond'", params.push(key))),
+            _ => Some(params.push(key.to_string())),
+        }
+    }
+
+    /// The cursor of a row selected with [`TIMESTAMP_KEY_COLUMNS`].
+    fn cursor(&self, row: &Row) -> Cursor {
+        Cursor {
+            sort: self.as_str(),
+            key: match self.column {
+                SortColumn::Id => None,
+                SortColumn::Name => Some(row.get("name")),
+                SortColumn::Email => Some(row.get("email")),
+                SortColumn::CreatedAt => Some(row.get::<_, i64>("created_at_key").to_string()),
+                SortColumn::UpdatedAt => Some(row.get::<_, i64>("updated_at_key").to_string()),
+            },
+            id: row.get::<_, i32>("id").into(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Filters {
+    q: Option<String>,
+    email_domain: Option<String>,
+    include_deleted: bool,
+    created_after: Option<i64>,
+    created_before: Option<i64>,
+}
+
+impl Filters {
+    /// Appends the filter conditions to `conditions`, binding values to `params`.
+    fn apply(&self, params: &mut Params, conditions: &mut Vec<String>) {
+        if !self.include_deleted {
+            conditions.push("deleted_at IS NULL".to_string());
+        }
+        if let Some(q) = &self.q {
+            let pattern = params.push(format!("%{}%", escape_like(q)));
+            conditions.push(format!("(name ILIKE {0} OR email ILIKE {0})", pattern));
+        }
+        if let Some(domain) = &self.email_domain {
+            let domain = params.push(domain.trim_start_matches('@').to_string());
+            conditions.push(format!("lower(split_part(email, '@', 2)) = lower({})", domain));
+        }
+        // Timestamps are truncated to whole seconds in the API, so "after"
+        // starts at the next second and "before" at the second itself.
+        if let Some(after) = self.created_after {
+            let after = params.push(after.saturating_add(1) as f64);
+            conditions.push(format!("created_at >= to_timestamp({})", after));
+        }
+        if let Some(before) = self.created_before {
+            let before = params.push(before as f64);
+            conditions.push(format!("created_at < to_timestamp({})", before));
+        }
+    }
+}
+
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+#[utoipa::path(
+    tag = "users",
+    params(
+        ("q" = Option<String>, Query, description = "Case-insensitive substring of name or email"),
+        ("email_domain" = Option<String>, Query, description = "Exact email domain, e.g. `example.com`"),
+        ("sort" = Option<String>, Query, description = "`id`, `name`, `email`, `created_at` or `updated_at`, prefixed with `-` for descending order"),
+        ("include_deleted" = Option<bool>, Query, description = "Also list deleted users that are not purged yet"),
+        ("created_after" = Option<i64>, Query, description = "Unix timestamp; only users created after it"),
+        ("created_before" = Option<i64>, Query, description = "Unix timestamp; only users created before it"),
+        PageParams
+    ),
+    responses(
+        (status = 200, description = "One page of users", body = UserPage),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key without users:read", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid parameters", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []), ("api_key" = []))
+)]
+#[get(
+    "/api/users?<q>&<email_domain>&<sort>&<include_deleted>&<created_after>&<created_before>&<page..>"
+)]
+#[allow(clippy::too_many_arguments)]
+async fn get_users(
+    _user: Viewer,
+    conn: Db,
+    q: Option<String>,
+    email_domain: Option<String>,
+    sort: Option<String>,
+    include_deleted: Option<bool>,
+    created_after: Option<i64>,
+    created_before: Option<i64>,
+    page: PageParams
+) -> Result<Json<Page<UserResponse>>, ApiError> {
+    let limit = page.limit()?;
+    let direction = page.direction()?;
+    let sort = Sort::parse(sort.as_deref())?;
+    let filters = Filters {
+        q: non_empty(q),
+        email_domain: non_empty(email_domain),
+        include_deleted: include_deleted.unwrap_or(false),
+        created_after: db::check_timestamp("created_after", created_after)?,
+        created_before: db::check_timestamp("created_before", created_before)?,
+    };
+    get_users_from_db(&conn, &filters, sort, limit, direction).await.map(Json)
+}
+
+async fn get_users_from_db(
+    client: &Client,
+    filters: &Filters,
+    sort: Sort,
+    limit: i64,
+    direction: Direction
+) -> Result<Page<UserResponse>, ApiError> {
+    let mut params = Params::default();
+    let mut conditions = Vec::new();
+    filters.apply(&mut params, &mut conditions);
+
+    let total = client
+        .query_one(
+            &format!("SELECT count(*) FROM users{}", where_clause(&conditions)),
+            &params.as_refs()
+        ).await?
+        .get(0);
+
+    // Walking backwards flips both the comparison and the order; the page is
+    // put back in display order by Page::from_rows.
+    let backwards = matches!(direction, Direction::Before(_));
+    let descending = sort.descending != backwards;
+
+    if let Direction::After(cursor) | Direction::Before(cursor) = &direction {
+        if cursor.sort != sort.as_str() || cursor.key.is_some() != sort.column_sql().is_some() {
+            let field = if backwards { "before" } else { "after" };
+            return Err(
+                ApiError::Validation(
+                    vec![FieldError::new(field, "invalid", "Cursor does not match the requested sort")]
+                )
+            );
+        }
+
+        let op = if descending { "<" } else { ">" };
+        let malformed = || {
+            let field = if backwards { "before" } else { "after" };
+            ApiError::Validation(vec![FieldError::new(field, "invalid", "Cursor is malformed")])
+        };
+        let id = i32::try_from(cursor.id).map_err(|_| malformed())?;
+        let id = params.push(id);
+        match (sort.column_sql(), &cursor.key) {
+            (Some(column), Some(key)) => {
+                let key = sort.push_key(&mut params, key).ok_or_else(malformed)?;
+                conditions.push(format!("({}, id) {} ({}, {})", column, op, key, id));
+            }
+            _ => conditions.push(format!("id {} {}", op, id)),
+        }
+    }
+
+    let order = if descending { "DESC" } else { "ASC" };
+    let order_by = match sort.column_sql() {
+        Some(column) => format!("{0} {1}, id {1}", column, order),
+        None => format!("id {}", order),
+    };
+    let fetch = params.push(limit + 1);
+
+    let rows = client.query(
+        &format!(
+            "SELECT {}, {} FROM users{} ORDER BY {} LIMIT {}",
+            USER_COLUMNS,
+            TIMESTAMP_KEY_COLUMNS,
+            where_clause(&conditions),
+            order_by,
+            fetch
+        ),
+        &params.as_refs()
+    ).await?;
+
+    let page = pagination::page_from_rows(rows, limit, &direction, total, |row| sort.cursor(row));
+    Ok(Page {
+        items: page.items.iter().map(user_from_row).collect(),
+        next_cursor: page.next_cursor,
+        prev_cursor: page.prev_cursor,
+        total: page.total,
+    })
+}
+
+const SEARCH_DEFAULT_LIMIT: i64 = 10;
+const SEARCH_MAX_LIMIT: i64 = 50;
+
+/// Turns free text into a prefix `tsquery` (`"ann smi"` becomes
+/// `ann:* & smi:*`), dropping everything tsquery would treat as syntax.
+fn prefix_tsquery(q: &str) -> String {
+    q.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("{}:*", term.to_lowercase()))
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+#[utoipa::path(
+    tag = "users",
+    params(
+        ("q" = String, Query, description = "Words to match as prefixes; typos are matched by similarity"),
+        ("limit" = Option<i64>, Query, description = "Between 1 and 50, defaults to 10")
+    ),
+    responses(
+        (status = 200, description = "Best matches first", body = Vec<SearchHit>),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key without users:read", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid parameters", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []), ("api_key" = []))
+)]
+#[get("/api/users/search?<q>&<limit>")]
+async fn search_users(
+    _user: Viewer,
+    conn: Db,
+    q: Option<String>,
+    limit: Option<i64>
+) -> Result<Json<Vec<SearchHit>>, ApiError> {
+    let Some(q) = non_empty(q) else {
+        return Err(
+            ApiError::Validation(vec![FieldError::new("q", "required", "Search text is required")])
+        );
+    };
+    let limit = limit.unwrap_or(SEARCH_DEFAULT_LIMIT);
+    if !(1..=SEARCH_MAX_LIMIT).contains(&limit) {
+        return Err(
+            ApiError::Validation(
+                vec![
+                    FieldError::new(
+                        "limit",
+                        "out_of_range",
+                        format!("Limit must be between 1 and {}", SEARCH_MAX_LIMIT)
+                    )
+                ]
+            )
+        );
+    }
+
+    // Full-text matches rank by ts_rank; trigram similarity catches typos and
+    // substrings that are not word prefixes.
+    let hits = conn
+        .query(
+            &format!(
+                "SELECT {},
+                    ts_rank(search, query) + greatest(similarity(name, $1), similarity(email, $1)) AS rank,
+                    ts_headline('simple', name, query, 'HighlightAll=true') AS name_snippet,
+                    ts_headline('simple', email, query, 'HighlightAll=true') AS email_snippet
+                FROM users, to_tsquery('simple', $2) AS query
+                WHERE (search @@ query OR name % $1 OR email % $1) AND deleted_at IS NULL
+                ORDER BY rank DESC, id
+                LIMIT $3",
+                USER_COLUMNS
+            ),
+            &[&q, &prefix_tsquery(&q), &limit]
+        ).await?
+        .iter()
+        .map(|row| SearchHit {
+            user: user_from_row(row),
+            rank: row.get("rank"),
+            name_snippet: row.get("name_snippet"),
+            email_snippet: row.get("email_snippet"),
+        })
+        .collect();
+
+    Ok(Json(hits))
+}
+
+#[utoipa::path(
+    tag = "users",
+    params(
+        ("include_deleted" = Option<bool>, Query, description = "Also return the user if it is deleted but not purged yet")
+    ),
+    responses(
+        (status = 200, description = "The user", body = UserResponse, headers(("ETag" = String, description = "The user's version, for `If-Match`"))),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key without users:read", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such user", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []), ("api_key" = []))
+)]
+#[get("/api/users/<id>?<include_deleted>")]
+async fn get_user(
+    _user: Viewer,
+    conn: Db,
+    id: i32,
+    include_deleted: Option<bool>
+) -> Result<Tagged, ApiError> {
+    get_user_from_db(&conn, id, include_deleted.unwrap_or(false)).await?
+        .map(Tagged)
+        .ok_or(ApiError::NotFound)
+}
+
+pub async fn get_user_from_db(
+    client: &Client,
+    id: i32,
+    include_deleted: bool
+) -> Result<Option<UserResponse>, ApiError> {
+    let user = client
+        .query_opt(
+            &format!("SELECT {} FROM users WHERE id = $1 AND ($2 OR deleted_at IS NULL)", USER_COLUMNS),
+            &[&id, &include_deleted]
+        ).await?
+        .map(|row| user_from_row(&row));
+
+    Ok(user)
+}
+
+/// Locks the user for the rest of the transaction and returns it as it is
+/// before the change, for the audit log.
+async fn lock_user(
+    client: &impl GenericClient,
+    id: i32,
+    include_deleted: bool
+) -> Result<UserResponse, ApiError> {
+    client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM users WHERE id = $1 AND ($2 OR deleted_at IS NULL) FOR UPDATE",
+                USER_COLUMNS
+            ),
+            &[&id, &include_deleted]
+        ).await?
+        .map(|row| user_from_row(&row))
+        .ok_or(ApiError::NotFound)
+}
+
+#[utoipa::path(
+    tag = "users",
+    request_body = UpdateUser,
+    params(("If-Match" = String, Header, description = "`ETag` of the user as last read")),
+    responses(
+        (status = 200, description = "User updated", body = UserResponse, headers(("ETag" = String))),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only editors, admins and keys with users:write can update users", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such user", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Email already in use", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Changed since it was read", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid user", body = Problem, content_type = "application/problem+json"),
+        (status = 428, description = "No If-Match header", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []), ("api_key" = []))
+)]
+#[put("/api/users/<id>", data = "<user>")]
+async fn update_user(
+    caller: Editor,
+    mut conn: Db,
+    context: AuditContext,
+    if_match: IfMatch,
+    id: i32,
+    user: Json<UpdateUser>
+) -> Result<Tagged, ApiError> {
+    let user = user.into_inner().validated().map_err(ApiError::Validation)?;
+    let patch = UserPatch { name: Some(user.name), email: Some(user.email) };
+    let tx = conn.transaction().await?;
+    let user = apply_patch(&tx, &context, (&*caller).into(), id, &if_match, patch).await?;
+    tx.commit().await?;
+
+    Ok(Tagged(user))
+}
+
+#[utoipa::path(
+    tag = "users",
+    request_body(content = UserPatch, content_type = "application/merge-patch+json"),
+    params(("If-Match" = String, Header, description = "`ETag` of the user as last read")),
+    responses(
+        (status = 200, description = "User updated; an empty patch changes nothing", body = UserResponse, headers(("ETag" = String))),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only editors, admins and keys with users:write can update users", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such user", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Email already in use", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Changed since it was read", body = Problem, content_type = "application/problem+json"),
+        (status = 415, description = "Not sent as application/merge-patch+json", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid patch, including `null` and unknown fields", body = Problem, content_type = "application/problem+json"),
+        (status = 428, description = "No If-Match header", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []), ("api_key" = []))
+)]
+#[patch("/api/users/<id>", format = "application/merge-patch+json", data = "<patch>")]
+async fn patch_user(
+    caller: Editor,
+    mut conn: Db,
+    context: AuditContext,
+    if_match: IfMatch,
+    id: i32,
+    patch: Json<UserPatch>
+) -> Result<Tagged, ApiError> {
+    let patch = patch.into_inner().validated().map_err(ApiError::Validation)?;
+    let tx = conn.transaction().await?;
+    let user = apply_patch(&tx, &context, (&*caller).into(), id, &if_match, patch).await?;
+    tx.commit().await?;
+
+    Ok(Tagged(user))
+}
+
+/// Writes the fields set in a validated `patch` to a live user, if its
+/// version matches, and records the change.
+pub async fn apply_patch(
+    client: &impl GenericClient,
+    context: &AuditContext,
+    actor: Actor,
+    id: i32,
+    if_match: &IfMatch,
+    patch: UserPatch
+) -> Result<UserResponse, ApiError> {
+    let before = lock_user(client, id, false).await?;
+    if_match.check(before.version)?;
+    // Nothing to write, so no new version and no audit event either.
+    if patch.is_empty() {
+        return Ok(before);
+    }
+
+    let mut params = Params::default();
+    let mut assignments = Vec::new();
+    if let Some(name) = patch.name {
+        assignments.push(format!("name = {}", params.push(name)));
+    }
+    if let Some(email) = patch.email {
+        assignments.push(format!("email = {}", params.push(email)));
+    }
+    let row = client.query_one(
+        &format!(
+            "UPDATE users SET {} WHERE id = {} RETURNING {}",
+            assignments.join(", "),
+            params.push(id),
+            USER_COLUMNS
+        ),
+        &params.as_refs()
+    ).await?;
+    let after = user_from_row(&row);
+    audit::record(client, context, actor, AuditAction::UserUpdate, id, Some(&before), Some(&after)).await?;
+
+    Ok(after)
+}
+
+/// Catches patches in any other format, which would otherwise look like a
+/// missing route.
+#[patch("/api/users/<_>", rank = 2)]
+fn patch_user_media_type() -> ApiError {
+    ApiError::Http(Status::UnsupportedMediaType)
+}
+
+#[utoipa::path(
+    tag = "users",
+    request_body = SetRole,
+    params(("If-Match" = String, Header, description = "`ETag` of the user as last read")),
+    responses(
+        (status = 200, description = "Role changed", body = UserResponse, headers(("ETag" = String))),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only admins can change roles", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such user", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Changed since it was read", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Admins cannot demote themselves", body = Problem, content_type = "application/problem+json"),
+        (status = 428, description = "No If-Match header", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []))
+)]
+#[put("/api/users/<id>/role", data = "<role>")]
+async fn set_role(
+    admin: Admin,
+    mut conn: Db,
+    context: AuditContext,
+    if_match: IfMatch,
+    id: i32,
+    role: Json<SetRole>
+) -> Result<Tagged, ApiError> {
+    // Demoting yourself could leave nobody able to promote anyone back.
+    if id == admin.id && role.role != Role::Admin {
+        return Err(
+            ApiError::Validation(
+                vec![FieldError::new("role", "self_demotion", "You cannot remove your own admin role")]
+            )
+        );
+    }
+
+    let tx = conn.transaction().await?;
+    let before = lock_user(&tx, id, false).await?;
+    if_match.check(before.version)?;
+    let row = tx.query_one(
+        &format!("UPDATE users SET role = $1 WHERE id = $2 RETURNING {}", USER_COLUMNS),
+        &[&role.role.as_str(), &id]
+    ).await?;
+    let after = user_from_row(&row);
+    audit::record(&tx, &context, (&*admin).into(), AuditAction::UserSetRole, id, Some(&before), Some(&after)).await?;
+    tx.commit().await?;
+
+    Ok(Tagged(after))
+}
+
+#[utoipa::path(
+    tag = "users",
+    params(("If-Match" = String, Header, description = "`ETag` of the user as last read")),
+    responses(
+        (status = 204, description = "User deleted; it can be restored until it is purged"),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only admins can delete users", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such user", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Changed since it was read", body = Problem, content_type = "application/problem+json"),
+        (status = 428, description = "No If-Match header", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []))
+)]
+#[delete("/api/users/<id>")]
+async fn delete_user(
+    admin: Admin,
+    mut conn: Db,
+    context: AuditContext,
+    if_match: IfMatch,
+    id: i32
+) -> Result<Status, ApiError> {
+    let tx = conn.transaction().await?;
+    soft_delete(&tx, &context, (&*admin).into(), id, &if_match).await?;
+    tx.commit().await?;
+
+    Ok(Status::NoContent)
+}
+
+/// Marks a live user deleted, if its version matches, and records it.
+pub async fn soft_delete(
+    client: &impl GenericClient,
+    context: &AuditContext,
+    actor: Actor,
+    id: i32,
+    if_match: &IfMatch
+) -> Result<UserResponse, ApiError> {
+    let before = lock_user(client, id, false).await?;
+    if_match.check(before.version)?;
+    let row = client.query_one(
+        &format!("UPDATE users SET deleted_at = now() WHERE id = $1 RETURNING {}", USER_COLUMNS),
+        &[&id]
+    ).await?;
+    let after = user_from_row(&row);
+    audit::record(client, context, actor, AuditAction::UserDelete, id, Some(&before), Some(&after)).await?;
+
+    Ok(after)
+}
+
+#[utoipa::path(
+    tag = "users",
+    responses(
+        (status = 200, description = "User restored; restoring a user that is not deleted changes nothing", body = UserResponse, headers(("ETag" = String))),
+        (status = 401, description = "Not logged in", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Only admins can restore users", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such user, or already purged", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Email taken by another user since the deletion", body = Problem, content_type = "application/problem+json")
+    ),
+    security(("session" = []), ("bearer" = []))
+)]
+#[post("/api/users/<id>/restore")]
+async fn restore_user(
+    admin: Admin,
+    mut conn: Db,
+    context: AuditContext,
+    id: i32
+) -> Result<Tagged, ApiError> {
+    let tx = conn.transaction().await?;
+    let before = lock_user(&tx, id, true).await?;
+    if before.deleted_at.is_none() {
+        return Ok(Tagged(before));
+    }
+
+    let row = tx.query_one(
+        &format!("UPDATE users SET deleted_at = NULL WHERE id = $1 RETURNING {}", USER_COLUMNS),
+        &[&id]
+    ).await?;
+    let after = user_from_row(&row);
+    audit::record(&tx, &context, (&*admin).into(), AuditAction::UserRestore, id, Some(&before), Some(&after)).await?;
+    tx.commit().await?;
+
+    Ok(Tagged(after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort(value: &str) -> Sort {
+        Sort::parse(Some(value)).ok().unwrap()
+    }
+
+    #[test]
+    fn parses_sorts() {
+        let default = Sort::parse(None).ok().unwrap();
+        assert!(default.column == SortColumn::Id && !default.descending);
+        assert!(sort("name").column == SortColumn::Name && !sort("name").descending);
+        assert!(sort("-created_at").column == SortColumn::CreatedAt && sort("-created_at").descending);
+        for value in ["id", "-id", "name", "-email", "created_at", "-updated_at"] {
+            assert_eq!(sort(value).as_str(), value);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_sorts() {
+        for value in ["", "-", "--name", "+name", "NAME", "password_hash", "name; DROP TABLE users", "name,id"] {
+            assert!(
+                matches!(Sort::parse(Some(value)), Err(ApiError::Validation(errors)) if errors[0].code == "unsupported"),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn binds_cursor_keys_by_column_type() {
+        let mut params = Params::default();
+        assert_eq!(sort("name").push_key(&mut params, "Ada' OR 1=1"), Some("$1".to_string()));
+        assert_eq!(
+            sort("-created_at").push_key(&mut params, "1700000000123456"),
+            Some("timestamptz 'epoch' + $2::bigint * interval '1 microsecond'".to_string())
+        );
+        for key in ["", "yesterday", "1.5", "1700000000123456; --", "99999999999999999999"] {
+            assert_eq!(sort("updated_at").push_key(&mut params, key), None, "{}", key);
+        }
+        assert_eq!(params.as_refs().len(), 2);
+    }
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(escape_like("ada"), "ada");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("a_b"), "a\\_b");
+        assert_eq!(escape_like("c:\\temp"), "c:\\\\temp");
+        assert_eq!(escape_like("\\%_"), "\\\\\\%\\_");
+    }
+
+    #[test]
+    fn strips_tsquery_syntax() {
+        assert_eq!(prefix_tsquery("ann smi"), "ann:* & smi:*");
+        assert_eq!(prefix_tsquery("o'n"), "o:* & n:*");
+        assert_eq!(prefix_tsquery("Ada & !Bob | (c:*)"), "ada:* & bob:* & c:*");
+        assert_eq!(prefix_tsquery("!!!"), "");
+        assert_eq!(prefix_tsquery("  "), "");
+        assert_eq!(prefix_tsquery("Zoë"), "zoë:*");
+    }
+}
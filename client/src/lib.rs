@@ -0,0 +1,300 @@
+//! Typed client for the `/api` endpoints. Built on `reqwest`, so the same code
+//! runs in the browser (through `fetch`) and natively against a live server.
+//! Either way the session cookie set by [`ApiClient::login`] is sent with
+//! every later request; services use [`ApiClient::with_bearer_token`] or
+//! [`ApiClient::with_api_key`] instead.
+
+use std::fmt;
+
+use reqwest::{ Method, RequestBuilder, Response };
+use reqwest::header::{ CONTENT_TYPE, IF_MATCH };
+use reqwest::multipart::{ Form, Part };
+use serde::de::DeserializeOwned;
+use shared::{
+    ApiKeyResponse,
+    AuditEvent,
+    BulkRequest,
+    BulkResponse,
+    CreateApiKey,
+    CreateUser,
+    CreatedApiKey,
+    ImportOptions,
+    ImportReport,
+    ListAudit,
+    ListUsers,
+    Login,
+    Page,
+    Problem,
+    RevokeToken,
+    Role,
+    SearchHit,
+    SetRole,
+    TokenRequest,
+    TokenResponse,
+    UpdateUser,
+    UserPatch,
+    UserResponse,
+};
+
+const MERGE_PATCH: &str = "application/merge-patch+json";
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request never got a response, or its body could not be read.
+    Http(reqwest::Error),
+    /// The server answered with an `application/problem+json` error.
+    Api(Problem),
+    /// The server answered with an error status but no problem body, e.g. from
+    /// a proxy in front of it.
+    Status(u16),
+}
+
+impl ClientError {
+    /// HTTP status of the response, if there was one.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ClientError::Http(e) => e.status().map(|status| status.as_u16()),
+            ClientError::Api(problem) => Some(problem.status),
+            ClientError::Status(status) => Some(*status),
+        }
+    }
+
+    pub fn problem(&self) -> Option<&Problem> {
+        match self {
+            ClientError::Api(problem) => Some(problem),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Http(e) => write!(f, "Request failed: {}", e),
+            ClientError::Api(problem) =>
+                match &problem.detail {
+                    Some(detail) => write!(f, "{} ({}): {}", problem.title, problem.code, detail),
+                    None => write!(f, "{} ({})", problem.title, problem.code),
+                }
+            ClientError::Status(status) => write!(f, "Request failed with status {}", status),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Http(e)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ApiClient {
+    base_url: String,
+    http: reqwest::Client,
+    bearer_token: Option<String>,
+    api_key: Option<String>,
+}
+
+impl ApiClient {
+    /// `base_url` is the server root, without the `/api` prefix.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        // Browsers keep cookies themselves; native clients need their own jar.
+        #[cfg(target_arch = "wasm32")]
+        let http = reqwest::Client::new();
+        #[cfg(not(target_arch = "wasm32"))]
+        let http = reqwest::Client
+            ::builder()
+            .cookie_store(true)
+            .build()
+            .expect("Failed to initialize the HTTP client");
+
+        ApiClient {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http,
+            bearer_token: None,
+            api_key: None,
+        }
+    }
+
+    /// Authenticates every request with an access token from [`ApiClient::token`].
+    pub fn with_bearer_token(self, token: impl Into<String>) -> Self {
+        ApiClient { bearer_token: Some(token.into()), ..self }
+    }
+
+    /// Authenticates every request with an API key from [`ApiClient::create_api_key`].
+    pub fn with_api_key(self, key: impl Into<String>) -> Self {
+        ApiClient { api_key: Some(key.into()), ..self }
+    }
+
+    pub async fn login(&self, login: &Login) -> Result<UserResponse, ClientError> {
+        json(self.request(Method::POST, "/api/auth/login").json(login)).await
+    }
+
+    pub async fn logout(&self) -> Result<(), ClientError> {
+        send(self.request(Method::POST, "/api/auth/logout")).await?;
+        Ok(())
+    }
+
+    pub async fn token(&self, request: &TokenRequest) -> Result<TokenResponse, ClientError> {
+        json(self.request(Method::POST, "/api/auth/token").json(request)).await
+    }
+
+    pub async fn revoke(&self, refresh_token: &str) -> Result<(), ClientError> {
+        let body = RevokeToken { refresh_token: refresh_token.to_string() };
+        send(self.request(Method::POST, "/api/auth/revoke").json(&body)).await?;
+        Ok(())
+    }
+
+    /// The logged-in user; fails with status 401 without a session.
+    pub async fn me(&self) -> Result<UserResponse, ClientError> {
+        json(self.request(Method::GET, "/api/auth/me")).await
+    }
+
+    pub async fn list_users(&self, query: &ListUsers) -> Result<Page<UserResponse>, ClientError> {
+        json(self.request(Method::GET, "/api/users").query(query)).await
+    }
+
+    pub async fn search_users(&self, q: &str, limit: i64) -> Result<Vec<SearchHit>, ClientError> {
+        let request = self
+            .request(Method::GET, "/api/users/search")
+            .query(&[("q", q.to_string()), ("limit", limit.to_string())]);
+        json(request).await
+    }
+
+    pub async fn get_user(&self, id: i32) -> Result<UserResponse, ClientError> {
+        json(self.request(Method::GET, &format!("/api/users/{}", id))).await
+    }
+
+    pub async fn create_user(&self, user: &CreateUser) -> Result<UserResponse, ClientError> {
+        json(self.request(Method::POST, "/api/users").json(user)).await
+    }
+
+    /// `version` is the [`UserResponse::version`] the change is based on; if
+    /// the user has changed since, this fails with status 412.
+    pub async fn update_user(&self, id: i32, user: &UpdateUser, version: i32) -> Result<UserResponse, ClientError> {
+        let request = self.request(Method::PUT, &format!("/api/users/{}", id)).header(IF_MATCH, etag(version));
+        json(request.json(user)).await
+    }
+
+    /// Changes only the fields set in `patch`. Fails with status 412 like
+    /// [`ApiClient::update_user`].
+    pub async fn patch_user(&self, id: i32, patch: &UserPatch, version: i32) -> Result<UserResponse, ClientError> {
+        let body = serde_json::to_string(patch).expect("UserPatch is serializable");
+        let request = self
+            .request(Method::PATCH, &format!("/api/users/{}", id))
+            .header(IF_MATCH, etag(version))
+            .header(CONTENT_TYPE, MERGE_PATCH)
+            .body(body);
+        json(request).await
+    }
+
+    /// Needs an admin. Fails with status 412 like [`ApiClient::update_user`].
+    pub async fn set_role(&self, id: i32, role: Role, version: i32) -> Result<UserResponse, ClientError> {
+        let request = self.request(Method::PUT, &format!("/api/users/{}/role", id)).header(IF_MATCH, etag(version));
+        json(request.json(&(SetRole { role }))).await
+    }
+
+    /// Fails with status 412 like [`ApiClient::update_user`].
+    pub async fn delete_user(&self, id: i32, version: i32) -> Result<(), ClientError> {
+        send(self.request(Method::DELETE, &format!("/api/users/{}", id)).header(IF_MATCH, etag(version))).await?;
+        Ok(())
+    }
+
+    /// Undoes [`delete_user`](Self::delete_user) until the user is purged.
+    pub async fn restore_user(&self, id: i32) -> Result<UserResponse, ClientError> {
+        json(self.request(Method::POST, &format!("/api/users/{}/restore", id))).await
+    }
+
+    /// Runs every operation in one transaction. Failed operations come back
+    /// as results, not as an `Err`; check [`BulkResponse::committed`].
+    pub async fn bulk_users(&self, request: &BulkRequest) -> Result<BulkResponse, ClientError> {
+        json(self.request(Method::POST, "/api/users/bulk").json(request)).await
+    }
+
+    /// Uploads `csv` as a file named `file_name`. Rows that could not be
+    /// imported are reported in the result rather than failing the request.
+    pub async fn import_users(
+        &self,
+        file_name: &str,
+        csv: Vec<u8>,
+        options: &ImportOptions
+    ) -> Result<ImportReport, ClientError> {
+        let file = Part::bytes(csv).file_name(file_name.to_string()).mime_str("text/csv")?;
+        let mut form = Form::new().part("file", file).text("dry_run", options.dry_run.to_string());
+        if let Some(column) = &options.name_column {
+            form = form.text("name_column", column.clone());
+        }
+        if let Some(column) = &options.email_column {
+            form = form.text("email_column", column.clone());
+        }
+        json(self.request(Method::POST, "/api/users/import").multipart(form)).await
+    }
+
+    /// Needs an admin.
+    pub async fn list_api_keys(&self) -> Result<Vec<ApiKeyResponse>, ClientError> {
+        json(self.request(Method::GET, "/api/keys")).await
+    }
+
+    /// Needs an admin. The returned key is not shown again.
+    pub async fn create_api_key(&self, api_key: &CreateApiKey) -> Result<CreatedApiKey, ClientError> {
+        json(self.request(Method::POST, "/api/keys").json(api_key)).await
+    }
+
+    /// Needs an admin.
+    pub async fn revoke_api_key(&self, id: i64) -> Result<(), ClientError> {
+        send(self.request(Method::DELETE, &format!("/api/keys/{}", id))).await?;
+        Ok(())
+    }
+
+    /// Needs an admin.
+    pub async fn list_audit(&self, query: &ListAudit) -> Result<Page<AuditEvent>, ClientError> {
+        json(self.request(Method::GET, "/api/audit").query(query)).await
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut request = self.http.request(method, format!("{}{}", self.base_url, path));
+        if let Some(token) = &self.bearer_token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(key) = &self.api_key {
+            request = request.header("X-Api-Key", key);
+        }
+        // The API is on another origin, where fetch leaves cookies out unless
+        // told otherwise.
+        #[cfg(target_arch = "wasm32")]
+        let request = request.fetch_credentials_include();
+        request
+    }
+}
+
+/// The `ETag` the backend gives a user at `version`.
+fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Sends the request and turns error statuses into [`ClientError`]s.
+async fn send(request: RequestBuilder) -> Result<Response, ClientError> {
+    let resp = request.send().await?;
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+
+    match resp.json::<Problem>().await {
+        Ok(problem) => Err(ClientError::Api(problem)),
+        Err(_) => Err(ClientError::Status(status.as_u16())),
+    }
+}
+
+async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, ClientError> {
+    Ok(send(request).await?.json().await?)
+}
@@ -0,0 +1,27 @@
+use std::collections::HashMap;
+
+use client::ApiClient;
+use shared::{ FieldError, Problem, codes };
+
+// Set API_BASE_URL at build time (e.g. `API_BASE_URL=https://api.example.com trunk build`)
+// to point the app at a backend other than the local development server.
+pub const API_BASE_URL: &str = match option_env!("API_BASE_URL") {
+    Some(url) => url,
+    None => "http://127.0.0.1:8000",
+};
+
+pub fn client() -> ApiClient {
+    ApiClient::new(API_BASE_URL)
+}
+
+pub fn field_errors(errors: Vec<FieldError>) -> HashMap<String, String> {
+    errors.into_iter().map(|e| (e.field, e.message)).collect()
+}
+
+pub fn problem_field_errors(problem: &Problem) -> HashMap<String, String> {
+    if problem.code == codes::EMAIL_TAKEN {
+        HashMap::from([("email".to_string(), "Email already in use".to_string())])
+    } else {
+        field_errors(problem.errors.clone())
+    }
+}
@@ -0,0 +1,241 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use serde_json::Value;
+use shared::{ ActorKind, AuditAction, AuditEvent, ListAudit, Page };
+
+use crate::Route;
+use crate::api;
+use crate::time::format_time;
+
+const PAGE_SIZE: i64 = 50;
+
+/// Describes the change as `field: old → new` for every field that differs.
+fn describe_changes(event: &AuditEvent) -> String {
+    let field = |user: &Option<Value>, name: &str| {
+        user.as_ref()
+            .and_then(|user| user.get(name))
+            .map(|value| value.as_str().map_or_else(|| value.to_string(), str::to_string))
+    };
+
+    match event.action {
+        AuditAction::UserCreate =>
+            format!("created {}", field(&event.after, "email").unwrap_or_default()),
+        AuditAction::UserDelete =>
+            format!("deleted {}", field(&event.before, "email").unwrap_or_default()),
+        AuditAction::UserRestore =>
+            format!("restored {}", field(&event.after, "email").unwrap_or_default()),
+        AuditAction::UserPurge =>
+            format!("purged {}", field(&event.before, "email").unwrap_or_default()),
+        AuditAction::UserSetPassword =>
+            format!("set the password of {}", field(&event.after, "email").unwrap_or_default()),
+        AuditAction::UserUpdate | AuditAction::UserSetRole =>
+            ["name", "email", "role"]
+                .iter()
+                .filter_map(|name| {
+                    let (before, after) = (field(&event.before, name), field(&event.after, name));
+                    (before != after).then(|| {
+                        format!("{}: {} → {}", name, before.unwrap_or_default(), after.unwrap_or_default())
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+    }
+}
+
+fn select_value(e: &Event) -> String {
+    e.target_dyn_into::<web_sys::HtmlSelectElement>().unwrap().value()
+}
+
+#[function_component(AuditLog)]
+pub fn audit_log() -> Html {
+    let page = use_state(|| None as Option<Page<AuditEvent>>);
+    let filters = use_state(ListAudit::default);
+    let target_id = use_state(String::new);
+    let message = use_state(|| "".to_string());
+
+    let get_events = {
+        let page = page.clone();
+        let message = message.clone();
+        Callback::from(move |query: ListAudit| {
+            let page = page.clone();
+            let message = message.clone();
+            spawn_local(async move {
+                match api::client().list_audit(&(ListAudit { limit: Some(PAGE_SIZE), ..query })).await {
+                    Ok(fetched_page) => {
+                        message.set("".into());
+                        page.set(Some(fetched_page));
+                    }
+                    Err(e) if e.status() == Some(401) => message.set("Log in to read the audit log".into()),
+                    Err(e) if e.status() == Some(403) => message.set("Only admins can read the audit log".into()),
+                    Err(_) => message.set("Failed to fetch the audit log".into()),
+                }
+            });
+        })
+    };
+
+    {
+        let get_events = get_events.clone();
+        use_effect_with((), move |_| get_events.emit(ListAudit::default()));
+    }
+
+    let apply = {
+        let filters = filters.clone();
+        let target_id = target_id.clone();
+        let message = message.clone();
+        let get_events = get_events.clone();
+        Callback::from(move |_| {
+            let target_id = match target_id.trim() {
+                "" => None,
+                id =>
+                    match id.parse() {
+                        Ok(id) => Some(id),
+                        Err(_) => {
+                            message.set("User id must be a number".into());
+                            return;
+                        }
+                    }
+            };
+            let query = ListAudit { target_id, ..(*filters).clone() };
+            filters.set(query.clone());
+            get_events.emit(query);
+        })
+    };
+
+    html! {
+        <div class="container mx-auto p-4">
+            <h1 class="text-4xl font-bold text-blue-500 mb-4">{ "Audit Log" }</h1>
+
+            <div class="mb-4">
+                <select
+                    onchange={Callback::from({
+                        let filters = filters.clone();
+                        move |e: Event| {
+                            let action = AuditAction::parse(&select_value(&e));
+                            filters.set(ListAudit { action, ..(*filters).clone() });
+                        }
+                    })}
+                    class="border rounded px-2 py-2 mr-2"
+                >
+                    <option value="" selected={filters.action.is_none()}>{ "Any action" }</option>
+                    { for AuditAction::ALL.iter().map(|action| html! {
+                        <option value={action.as_str()} selected={filters.action == Some(*action)}>
+                            { action.as_str() }
+                        </option>
+                    }) }
+                </select>
+                <select
+                    onchange={Callback::from({
+                        let filters = filters.clone();
+                        move |e: Event| {
+                            let actor_kind = ActorKind::parse(&select_value(&e));
+                            filters.set(ListAudit { actor_kind, ..(*filters).clone() });
+                        }
+                    })}
+                    class="border rounded px-2 py-2 mr-2"
+                >
+                    <option value="" selected={filters.actor_kind.is_none()}>{ "Any actor" }</option>
+                    { for ActorKind::ALL.iter().map(|kind| html! {
+                        <option value={kind.as_str()} selected={filters.actor_kind == Some(*kind)}>
+                            { kind.as_str() }
+                        </option>
+                    }) }
+                </select>
+                <input
+                    placeholder="User id"
+                    value={(*target_id).clone()}
+                    oninput={Callback::from({
+                        let target_id = target_id.clone();
+                        move |e: InputEvent| {
+                            let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                            target_id.set(input.value());
+                        }
+                    })}
+                    class="border rounded px-4 py-2 mr-2"
+                />
+                <button
+                    onclick={apply}
+                    class="bg-gray-500 hover:bg-gray-700 text-white font-bold py-2 px-4 rounded"
+                >
+                    { "Filter" }
+                </button>
+            </div>
+
+            if !message.is_empty() {
+                <p class="text-red-500 mb-4">{ &*message }</p>
+            }
+
+            if let Some(page) = &*page {
+                <table class="table-auto">
+                    <thead>
+                        <tr>
+                            <th class="text-left px-2 py-1">{ "Time" }</th>
+                            <th class="text-left px-2 py-1">{ "Actor" }</th>
+                            <th class="text-left px-2 py-1">{ "Action" }</th>
+                            <th class="text-left px-2 py-1">{ "User" }</th>
+                            <th class="text-left px-2 py-1">{ "Changes" }</th>
+                            <th class="text-left px-2 py-1">{ "Request" }</th>
+                            <th class="text-left px-2 py-1">{ "IP" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for page.items.iter().map(|event| html! {
+                            <tr>
+                                <td class="px-2 py-1">{ format_time(event.created_at) }</td>
+                                <td class="px-2 py-1">
+                                    if event.actor_kind == ActorKind::System {
+                                        { "system" }
+                                    } else {
+                                        { format!(
+                                            "{} {} (#{})",
+                                            event.actor_kind.as_str(),
+                                            event.actor_name.as_deref().unwrap_or("(gone)"),
+                                            event.actor_id
+                                        ) }
+                                    }
+                                </td>
+                                <td class="px-2 py-1">{ event.action.as_str() }</td>
+                                <td class="px-2 py-1">
+                                    if event.action == AuditAction::UserPurge {
+                                        { format!("#{}", event.target_id) }
+                                    } else {
+                                        <Link<Route> to={Route::User { id: event.target_id }} classes="text-blue-500">
+                                            { format!("#{}", event.target_id) }
+                                        </Link<Route>>
+                                    }
+                                </td>
+                                <td class="px-2 py-1">{ describe_changes(event) }</td>
+                                <td class="px-2 py-1 text-gray-500"><code>{ &event.request_id }</code></td>
+                                <td class="px-2 py-1 text-gray-500">{ event.ip.as_deref().unwrap_or("") }</td>
+                            </tr>
+                        }) }
+                    </tbody>
+                </table>
+
+                <div class="mt-4 flex items-center">
+                    <button
+                        disabled={page.prev_cursor.is_none()}
+                        onclick={get_events.reform({
+                            let query = ListAudit { before: page.prev_cursor.clone(), ..(*filters).clone() };
+                            move |_| query.clone()
+                        })}
+                        class="bg-gray-300 hover:bg-gray-400 disabled:opacity-50 font-bold py-1 px-3 rounded"
+                    >
+                        { "Newer" }
+                    </button>
+                    <span class="mx-4 text-gray-700">{ format!("{} events", page.total) }</span>
+                    <button
+                        disabled={page.next_cursor.is_none()}
+                        onclick={get_events.reform({
+                            let query = ListAudit { after: page.next_cursor.clone(), ..(*filters).clone() };
+                            move |_| query.clone()
+                        })}
+                        class="bg-gray-300 hover:bg-gray-400 disabled:opacity-50 font-bold py-1 px-3 rounded"
+                    >
+                        { "Older" }
+                    </button>
+                </div>
+            }
+        </div>
+    }
+}
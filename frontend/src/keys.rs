@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use yew::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use client::ClientError;
+use shared::{ ApiKeyResponse, CreateApiKey, CreatedApiKey, Scope };
+
+use crate::api::{ self, problem_field_errors };
+use crate::time::format_time;
+
+fn status(key: &ApiKeyResponse) -> &'static str {
+    let now = (js_sys::Date::now() / 1000.0) as i64;
+    if key.revoked_at.is_some() {
+        "revoked"
+    } else if key.expires_at.is_some_and(|expires_at| expires_at <= now) {
+        "expired"
+    } else {
+        "active"
+    }
+}
+
+#[function_component(ApiKeys)]
+pub fn api_keys() -> Html {
+    let keys = use_state(|| None as Option<Vec<ApiKeyResponse>>);
+    let name = use_state(String::new);
+    let scopes = use_state(|| vec![Scope::UsersRead]);
+    let expires_in_days = use_state(String::new);
+    // The new key, shown until the page is left since it cannot be fetched again.
+    let created = use_state(|| None as Option<CreatedApiKey>);
+    let message = use_state(|| "".to_string());
+    let field_errors = use_state(HashMap::<String, String>::new);
+
+    let get_keys = {
+        let keys = keys.clone();
+        let message = message.clone();
+        Callback::from(move |_| {
+            let keys = keys.clone();
+            let message = message.clone();
+            spawn_local(async move {
+                match api::client().list_api_keys().await {
+                    Ok(fetched_keys) => keys.set(Some(fetched_keys)),
+                    Err(e) if e.status() == Some(401) => message.set("Log in to manage API keys".into()),
+                    Err(e) if e.status() == Some(403) => message.set("Only admins can manage API keys".into()),
+                    Err(_) => message.set("Failed to fetch API keys".into()),
+                }
+            });
+        })
+    };
+
+    {
+        let get_keys = get_keys.clone();
+        use_effect_with((), move |_| get_keys.emit(()));
+    }
+
+    let create_key = {
+        let name = name.clone();
+        let scopes = scopes.clone();
+        let expires_in_days = expires_in_days.clone();
+        let created = created.clone();
+        let message = message.clone();
+        let field_errors = field_errors.clone();
+        let get_keys = get_keys.clone();
+
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let name = name.clone();
+            let created = created.clone();
+            let message = message.clone();
+            let field_errors = field_errors.clone();
+            let get_keys = get_keys.clone();
+
+            let expires_in_days = match expires_in_days.trim() {
+                "" => None,
+                days =>
+                    match days.parse() {
+                        Ok(days) => Some(days),
+                        Err(_) => {
+                            message.set("Please fix the highlighted fields".into());
+                            field_errors.set(
+                                HashMap::from([
+                                    ("expires_in_days".to_string(), "Expiry must be a number of days".to_string()),
+                                ])
+                            );
+                            return;
+                        }
+                    }
+            };
+            // Catch what the shared rules reject before making a round trip.
+            let api_key = CreateApiKey { name: (*name).clone(), scopes: (*scopes).clone(), expires_in_days };
+            let api_key = match api_key.validated() {
+                Ok(api_key) => api_key,
+                Err(errors) => {
+                    message.set("Please fix the highlighted fields".into());
+                    field_errors.set(api::field_errors(errors));
+                    return;
+                }
+            };
+
+            spawn_local(async move {
+                match api::client().create_api_key(&api_key).await {
+                    Ok(new_key) => {
+                        message.set("".into());
+                        field_errors.set(HashMap::new());
+                        name.set(String::new());
+                        created.set(Some(new_key));
+                        get_keys.emit(());
+                    }
+
+                    Err(ClientError::Api(problem)) if problem.status == 422 => {
+                        message.set("Please fix the highlighted fields".into());
+                        field_errors.set(problem_field_errors(&problem));
+                    }
+
+                    Err(e) if e.status() == Some(403) => message.set("Only admins can manage API keys".into()),
+
+                    _ => message.set("Failed to create API key".into()),
+                }
+            });
+        })
+    };
+
+    let revoke_key = {
+        let message = message.clone();
+        let get_keys = get_keys.clone();
+
+        Callback::from(move |id: i64| {
+            let message = message.clone();
+            let get_keys = get_keys.clone();
+
+            spawn_local(async move {
+                match api::client().revoke_api_key(id).await {
+                    Ok(()) => {
+                        message.set("API key revoked".into());
+                        get_keys.emit(());
+                    }
+
+                    _ => message.set("Failed to revoke API key".into()),
+                }
+            });
+        })
+    };
+
+    let toggle_scope = |scope: Scope| {
+        let scopes = scopes.clone();
+        Callback::from(move |e: Event| {
+            let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            let mut new_scopes = (*scopes).clone();
+            new_scopes.retain(|s| *s != scope);
+            if input.checked() {
+                new_scopes.push(scope);
+            }
+            scopes.set(new_scopes);
+        })
+    };
+
+    html! {
+        <div class="container mx-auto p-4">
+            <h1 class="text-4xl font-bold text-blue-500 mb-4">{ "API Keys" }</h1>
+
+            if let Some(new_key) = &*created {
+                <div class="mb-4 p-4 border rounded bg-yellow-50">
+                    <p class="font-semibold">
+                        { format!("Key \"{}\" created. Copy it now; it will not be shown again.", new_key.api_key.name) }
+                    </p>
+                    <code class="block mt-2 break-all">{ &new_key.key }</code>
+                </div>
+            }
+
+            if keys.is_some() {
+                <form onsubmit={create_key} class="mb-4">
+                    <input
+                        placeholder="Name"
+                        value={(*name).clone()}
+                        oninput={Callback::from({
+                            let name = name.clone();
+                            move |e: InputEvent| {
+                                let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                name.set(input.value());
+                            }
+                        })}
+                        class="border rounded px-4 py-2 mr-2"
+                    />
+                    { for Scope::ALL.iter().map(|&scope| html! {
+                        <label class="mr-4">
+                            <input
+                                type="checkbox"
+                                checked={scopes.contains(&scope)}
+                                onchange={toggle_scope(scope)}
+                                class="mr-1"
+                            />
+                            { scope.as_str() }
+                        </label>
+                    }) }
+                    <input
+                        placeholder="Expires in days (optional)"
+                        value={(*expires_in_days).clone()}
+                        oninput={Callback::from({
+                            let expires_in_days = expires_in_days.clone();
+                            move |e: InputEvent| {
+                                let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                expires_in_days.set(input.value());
+                            }
+                        })}
+                        class="border rounded px-4 py-2 mr-2"
+                    />
+                    <button
+                        type="submit"
+                        class="bg-blue-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded"
+                    >
+                        { "Create Key" }
+                    </button>
+                    { for ["name", "scopes", "expires_in_days"].iter().filter_map(|field| field_errors.get(*field)).map(|error| html! {
+                        <p class="text-red-500 text-sm">{ error }</p>
+                    }) }
+                </form>
+            }
+
+            if !message.is_empty() {
+                <p class="text-green-500 mb-4">{ &*message }</p>
+            }
+
+            if let Some(keys) = &*keys {
+                <table class="table-auto">
+                    <thead>
+                        <tr>
+                            <th class="text-left px-2 py-1">{ "Name" }</th>
+                            <th class="text-left px-2 py-1">{ "Prefix" }</th>
+                            <th class="text-left px-2 py-1">{ "Scopes" }</th>
+                            <th class="text-left px-2 py-1">{ "Created" }</th>
+                            <th class="text-left px-2 py-1">{ "Expires" }</th>
+                            <th class="text-left px-2 py-1">{ "Last used" }</th>
+                            <th class="text-left px-2 py-1">{ "Status" }</th>
+                            <th></th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for keys.iter().map(|key| {
+                            let key_id = key.id;
+                            let status = status(key);
+                            html! {
+                                <tr>
+                                    <td class="px-2 py-1 font-semibold">{ &key.name }</td>
+                                    <td class="px-2 py-1"><code>{ format!("uk_{}_…", key.prefix) }</code></td>
+                                    <td class="px-2 py-1">
+                                        { key.scopes.iter().map(Scope::as_str).collect::<Vec<_>>().join(", ") }
+                                    </td>
+                                    <td class="px-2 py-1">{ format_time(key.created_at) }</td>
+                                    <td class="px-2 py-1">{ key.expires_at.map_or("never".to_string(), format_time) }</td>
+                                    <td class="px-2 py-1">{ key.last_used_at.map_or("never".to_string(), format_time) }</td>
+                                    <td class="px-2 py-1">{ status }</td>
+                                    <td class="px-2 py-1">
+                                        if status == "active" {
+                                            <button
+                                                onclick={revoke_key.reform(move |_| key_id)}
+                                                class="ml-4 bg-red-500 hover:bg-red-700 text-white font-bold py-1 px-2 rounded"
+                                            >
+                                                { "Revoke" }
+                                            </button>
+                                        }
+                                    </td>
+                                </tr>
+                            }
+                        }) }
+                    </tbody>
+                </table>
+            }
+        </div>
+    }
+}
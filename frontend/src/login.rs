@@ -0,0 +1,86 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use shared::Login;
+
+use crate::{ Route, Session };
+use crate::api;
+
+#[function_component(LoginPage)]
+pub fn login_page() -> Html {
+    let credentials = use_state(|| ("".to_string(), "".to_string()));
+    let message = use_state(|| "".to_string());
+    let session = use_context::<Session>().expect("Session is provided by App");
+    let navigator = use_navigator().unwrap();
+
+    let login = {
+        let credentials = credentials.clone();
+        let message = message.clone();
+
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let (email, password) = (*credentials).clone();
+            let message = message.clone();
+            let session = session.clone();
+            let navigator = navigator.clone();
+
+            spawn_local(async move {
+                match api::client().login(&(Login { email, password })).await {
+                    Ok(user) => {
+                        session.set(Some(user));
+                        navigator.push(&Route::Users);
+                    }
+
+                    Err(e) if e.status() == Some(401) =>
+                        message.set("Email or password is incorrect".into()),
+
+                    _ => message.set("Failed to log in".into()),
+                }
+            });
+        })
+    };
+
+    html! {
+        <div class="container mx-auto p-4">
+            <h1 class="text-4xl font-bold text-blue-500 mb-4">{ "Log in" }</h1>
+            <form onsubmit={login} class="mb-4">
+                <input
+                    type="email"
+                    placeholder="Email"
+                    value={credentials.0.clone()}
+                    oninput={Callback::from({
+                        let credentials = credentials.clone();
+                        move |e: InputEvent| {
+                            let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                            credentials.set((input.value(), credentials.1.clone()));
+                        }
+                    })}
+                    class="border rounded px-4 py-2 mr-2"
+                />
+                <input
+                    type="password"
+                    placeholder="Password"
+                    value={credentials.1.clone()}
+                    oninput={Callback::from({
+                        let credentials = credentials.clone();
+                        move |e: InputEvent| {
+                            let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                            credentials.set((credentials.0.clone(), input.value()));
+                        }
+                    })}
+                    class="border rounded px-4 py-2 mr-2"
+                />
+                <button
+                    type="submit"
+                    class="bg-blue-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded"
+                >
+                    { "Log in" }
+                </button>
+            </form>
+
+            if !message.is_empty() {
+                <p class="text-red-500 mt-2">{ &*message }</p>
+            }
+        </div>
+    }
+}
@@ -1,222 +1,130 @@
+mod api;
+mod audit;
+mod keys;
+mod login;
+mod time;
+mod toast;
+mod user_detail;
+mod user_import;
+mod user_list;
+
 use yew::prelude::*;
-use serde::{ Deserialize, Serialize };
-use gloo::net::http::Request;
+use yew_router::prelude::*;
 use wasm_bindgen_futures::spawn_local;
+use shared::{ Role, UserResponse };
+
+use audit::AuditLog;
+use keys::ApiKeys;
+use login::LoginPage;
+use toast::{ LastDeleted, UndoToast };
+use user_detail::UserDetail;
+use user_import::UserImport;
+use user_list::UserList;
+
+#[derive(Clone, Routable, PartialEq)]
+pub enum Route {
+    #[at("/")]
+    Users,
+    #[at("/users/import")]
+    Import,
+    #[at("/users/:id")]
+    User {
+        id: i32,
+    },
+    #[at("/login")]
+    Login,
+    #[at("/keys")]
+    Keys,
+    #[at("/audit")]
+    Audit,
+    #[not_found]
+    #[at("/404")]
+    NotFound,
+}
 
-#[function_component(App)]
-fn app() -> Html {
-    let user_state = use_state(|| ("".to_string(), "".to_string(), None as Option<i32>));
-    let message = use_state(|| "".to_string());
-    let users = use_state(Vec::new);
+fn switch(route: Route) -> Html {
+    match route {
+        Route::Users => html! { <UserList /> },
+        Route::Import => html! { <UserImport /> },
+        Route::User { id } => html! { <UserDetail {id} /> },
+        Route::Login => html! { <LoginPage /> },
+        Route::Keys => html! { <ApiKeys /> },
+        Route::Audit => html! { <AuditLog /> },
+        Route::NotFound =>
+            html! {
+                <div class="container mx-auto p-4">
+                    <p class="text-red-500">{ "Page not found" }</p>
+                    <Link<Route> to={Route::Users} classes="text-blue-500">{ "Back to users" }</Link<Route>>
+                </div>
+            },
+    }
+}
 
-    let get_users = {
-        let users = users.clone();
-        let message = message.clone();
-        Callback::from(move |_| {
-            let users = users.clone();
-            let message = message.clone();
-            spawn_local(async move {
-                match Request::get("http://127.0.0.1:8000/api/users").send().await {
-                    Ok(resp) if resp.ok() => {
-                        let fetched_users: Vec<User> = resp.json().await.unwrap_or_default();
-                        users.set(fetched_users);
-                    }
+/// The logged-in user, if any, shared with every page.
+pub type Session = UseStateHandle<Option<UserResponse>>;
 
-                    _ => message.set("Failed to fetch users".into()),
-                }
-            });
-        })
-    };
+#[function_component(SessionBar)]
+fn session_bar() -> Html {
+    let session = use_context::<Session>().expect("Session is provided by App");
 
-    let create_user = {
-        let user_state = user_state.clone();
-        let message = message.clone();
-        let get_users = get_users.clone();
+    let logout = {
+        let session = session.clone();
         Callback::from(move |_| {
-            let (name, email, _) = (*user_state).clone();
-            let user_state = user_state.clone();
-            let message = message.clone();
-            let get_users = get_users.clone();
-
+            let session = session.clone();
             spawn_local(async move {
-                let user_data = serde_json::json!({ "name": name, "email": email });
-
-                let response = Request::post("http://127.0.0.1:8000/api/users")
-                    .header("Content-Type", "application/json")
-                    .body(user_data.to_string())
-                    .send().await;
-
-                match response {
-                    Ok(resp) if resp.ok() => {
-                        message.set("User created successfully".into());
-                        get_users.emit(());
-                    }
-
-                    _ => message.set("Failed to create user".into()),
+                if api::client().logout().await.is_ok() {
+                    session.set(None);
                 }
-
-                user_state.set(("".to_string(), "".to_string(), None));
             });
         })
     };
 
-    let update_user = {
-        let user_state = user_state.clone();
-        let message = message.clone();
-        let get_users = get_users.clone();
-
-        Callback::from(move |_| {
-            let (name, email, editing_user_id) = (*user_state).clone();
-            let user_state = user_state.clone();
-            let message = message.clone();
-            let get_users = get_users.clone();
-
-            if let Some(id) = editing_user_id {
-                spawn_local(async move {
-                    let response = Request::put(&format!("http://127.0.0.1:8000/api/users/{}", id))
-                        .header("Content-Type", "application/json")
-                        .body(serde_json::to_string(&(id, name.as_str(), email.as_str())).unwrap())
-                        .send().await;
-
-                    match response {
-                        Ok(resp) if resp.ok() => {
-                            message.set("User updated successfully".into());
-                            get_users.emit(());
-                        }
-
-                        _ => message.set("Failed to update user".into()),
-                    }
-
-                    user_state.set(("".to_string(), "".to_string(), None));
-                });
+    html! {
+        <div class="container mx-auto px-4 pt-4 text-right text-gray-700">
+            if let Some(user) = &*session {
+                if user.role == Role::Admin {
+                    <Link<Route> to={Route::Audit} classes="mr-4 text-blue-500">{ "Audit log" }</Link<Route>>
+                    <Link<Route> to={Route::Keys} classes="mr-4 text-blue-500">{ "API keys" }</Link<Route>>
+                }
+                { format!("Logged in as {}", user.name) }
+                <button onclick={logout} class="ml-4 text-blue-500">{ "Log out" }</button>
+            } else {
+                <Link<Route> to={Route::Login} classes="text-blue-500">{ "Log in" }</Link<Route>>
             }
-        })
-    };
-
-    let delete_user = {
-        let message = message.clone();
-        let get_users = get_users.clone();
+        </div>
+    }
+}
 
-        Callback::from(move |id: i32| {
-            let message = message.clone();
-            let get_users = get_users.clone();
+#[function_component(App)]
+fn app() -> Html {
+    let session: Session = use_state(|| None);
+    let last_deleted: LastDeleted = use_state(|| None);
 
+    {
+        let session = session.clone();
+        use_effect_with((), move |_| {
             spawn_local(async move {
-                let response = Request::delete(
-                    &format!("http://127.0.0.1:8000/api/users/{}", id)
-                ).send().await;
-
-                match response {
-                    Ok(resp) if resp.ok() => {
-                        message.set("User deleted successfully".into());
-                        get_users.emit(());
-                    }
-
-                    _ => message.set("Failed to delete user".into()),
+                // Picks up a session that survived a page reload.
+                if let Ok(user) = api::client().me().await {
+                    session.set(Some(user));
                 }
             });
-        })
-    };
-
-    let edit_user = {
-        let user_state = user_state.clone();
-        let users = users.clone();
-
-        Callback::from(move |id: i32| {
-            if let Some(user) = users.iter().find(|u| u.id == id) {
-                user_state.set((user.name.clone(), user.email.clone(), Some(id)));
-            }
-        })
-    };
-
-    //html
+        });
+    }
 
     html! {
-        <div class="container mx-auto p-4">
-            <h1 class="text-4xl font-bold text-blue-500 mb-4">{ "User Management" }</h1>
-                <div class="mb-4">
-                    <input
-                        placeholder="Name"
-                        value={user_state.0.clone()}
-                        oninput={Callback::from({
-                            let user_state = user_state.clone();
-                            move |e: InputEvent| {
-                                let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
-                                user_state.set((input.value(), user_state.1.clone(), user_state.2));
-                            }
-                        })}
-                        class="border rounded px-4 py-2 mr-2"
-                    />
-                    <input
-                        placeholder="Email"
-                        value={user_state.1.clone()}
-                        oninput={Callback::from({
-                            let user_state = user_state.clone();
-                            move |e: InputEvent| {
-                                let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
-                                user_state.set((user_state.0.clone(), input.value(), user_state.2));
-                            }
-                        })}
-                        class="border rounded px-4 py-2 mr-2"
-                    />
-
-                    <button
-                        onclick={if user_state.2.is_some() { update_user.clone() } else { create_user.clone() }}
-                        class="bg-blue-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded"
-                    >
-                        { if user_state.2.is_some() { "Update User" } else { "Create User" } }
-                        
-                    </button>
-                        if !message.is_empty() {
-                        <p class="text-green-500 mt-2">{ &*message }</p>
-                    }
-                </div>
-
-                <button
-                    onclick={get_users.reform(|_| ())}  
-                    class="bg-gray-500 hover:bg-gray-700 text-white font-bold py-2 px-4 rounded mb-4"
-                >
-                    { "Fetch User List" }
-                </button>
-
-                <h2 class="text-2xl font-bold text-gray-700 mb-2">{ "User List" }</h2>
-
-                <ul class="list-disc pl-5">
-                    { for (*users).iter().map(|user| {
-                        let user_id = user.id;
-                        html! {
-                            <li class="mb-2">
-                                <span class="font-semibold">{ format!("ID: {}, Name: {}, Email: {}", user.id, user.name, user.email) }</span>
-                                <button
-                                    onclick={delete_user.clone().reform(move |_| user_id)}
-                                    class="ml-4 bg-red-500 hover:bg-red-700 text-white font-bold py-1 px-2 rounded"
-                                >
-                                    { "Delete" }
-                                </button>
-                                <button
-                                    onclick={edit_user.clone().reform(move |_| user_id)}
-                                    class="ml-4 bg-yellow-500 hover:bg-yellow-700 text-white font-bold py-1 px-2 rounded"
-                                >
-                                    { "Edit" }
-                                </button>
-                            </li>
-                        }
-                    })}
-
-                </ul>
-                    
-
-        </div>
+        <ContextProvider<Session> context={session}>
+            <ContextProvider<LastDeleted> context={last_deleted}>
+                <BrowserRouter>
+                    <SessionBar />
+                    <Switch<Route> render={switch} />
+                    <UndoToast />
+                </BrowserRouter>
+            </ContextProvider<LastDeleted>>
+        </ContextProvider<Session>>
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct User {
-    id: i32,
-    name: String,
-    email: String,
-}
-
 fn main() {
     yew::Renderer::<App>::new().render();
 }
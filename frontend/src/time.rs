@@ -0,0 +1,38 @@
+use js_sys::{ Array, Intl, Object, Reflect };
+use wasm_bindgen::JsValue;
+
+/// Formats a Unix timestamp in the browser's locale and time zone.
+pub fn format_time(secs: i64) -> String {
+    js_sys::Date
+        ::new(&JsValue::from_f64((secs as f64) * 1000.0))
+        .to_locale_string("default", &JsValue::UNDEFINED)
+        .into()
+}
+
+const UNITS: [(i64, &str); 7] = [
+    (365 * 86400, "year"),
+    (30 * 86400, "month"),
+    (7 * 86400, "week"),
+    (86400, "day"),
+    (3600, "hour"),
+    (60, "minute"),
+    (1, "second"),
+];
+
+/// Formats a Unix timestamp relative to now in the browser's locale, in its
+/// largest whole unit: "5 minutes ago", "yesterday", "now".
+pub fn relative_time(secs: i64) -> String {
+    let delta = secs - (js_sys::Date::now() / 1000.0) as i64;
+    let (size, unit) = UNITS
+        .iter()
+        .find(|(size, _)| delta.abs() >= *size)
+        .unwrap_or(&UNITS[UNITS.len() - 1]);
+
+    // "auto" says "yesterday" rather than "1 day ago".
+    let options = Object::new();
+    Reflect::set(&options, &"numeric".into(), &"auto".into()).expect("options is a plain object");
+    Intl::RelativeTimeFormat
+        ::new(&Array::new(), &options)
+        .format((delta / size) as f64, unit)
+        .into()
+}
@@ -0,0 +1,83 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+use gloo::timers::callback::Timeout;
+use wasm_bindgen_futures::spawn_local;
+use shared::UserResponse;
+
+use crate::Route;
+use crate::api;
+
+// Long enough to notice a mistake; the backend keeps the user far longer.
+const UNDO_TOAST_MS: u32 = 10_000;
+
+/// The user just deleted, offered for undo by [`UndoToast`]. Provided by the
+/// app rather than a page, so the toast survives leaving a deleted user's page.
+pub type LastDeleted = UseStateHandle<Option<UserResponse>>;
+
+#[function_component(UndoToast)]
+pub fn undo_toast() -> Html {
+    let last_deleted = use_context::<LastDeleted>().expect("LastDeleted is provided by App");
+    let message = use_state(|| "".to_string());
+    let navigator = use_navigator().unwrap();
+    let timer = use_mut_ref(|| None as Option<Timeout>);
+
+    {
+        let last_deleted = last_deleted.clone();
+        let message = message.clone();
+        use_effect_with(last_deleted.as_ref().map(|user| user.id), move |id| {
+            // Dropping the pending timeout cancels it.
+            *timer.borrow_mut() = None;
+            if id.is_some() {
+                message.set("".into());
+                *timer.borrow_mut() = Some(Timeout::new(UNDO_TOAST_MS, move || last_deleted.set(None)));
+            }
+        });
+    }
+
+    let undo = {
+        let last_deleted = last_deleted.clone();
+        let message = message.clone();
+        Callback::from(move |id: i32| {
+            let last_deleted = last_deleted.clone();
+            let message = message.clone();
+            let navigator = navigator.clone();
+            spawn_local(async move {
+                match api::client().restore_user(id).await {
+                    Ok(_) => {
+                        last_deleted.set(None);
+                        navigator.push(&Route::User { id });
+                    }
+
+                    Err(e) if e.status() == Some(409) =>
+                        message.set("Another user has taken the email since".into()),
+
+                    Err(e) if e.status() == Some(404) => message.set("User was already purged".into()),
+
+                    Err(e) if e.status() == Some(403) => message.set("Only admins can restore users".into()),
+
+                    _ => message.set("Failed to restore user".into()),
+                }
+            });
+        })
+    };
+
+    html! {
+        if let Some(user) = &*last_deleted {
+            <div class="fixed bottom-4 left-1/2 -translate-x-1/2 bg-gray-800 text-white rounded shadow px-4 py-2">
+                { format!("Deleted {}", user.name) }
+                <button
+                    onclick={undo.reform({
+                        let id = user.id;
+                        move |_| id
+                    })}
+                    class="ml-4 font-bold text-yellow-300 hover:text-yellow-100"
+                >
+                    { "Undo" }
+                </button>
+                if !message.is_empty() {
+                    <p class="text-red-300 text-sm">{ &*message }</p>
+                }
+            </div>
+        }
+    }
+}
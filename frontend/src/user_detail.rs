@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+
+use yew::prelude::*;
+use yew_router::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use client::ClientError;
+use shared::{ Role, UserPatch, UserResponse };
+
+use crate::{ Route, Session };
+use crate::api::{ self, problem_field_errors };
+use crate::time::{ format_time, relative_time };
+use crate::toast::LastDeleted;
+
+#[derive(Properties, PartialEq)]
+pub struct UserDetailProps {
+    pub id: i32,
+}
+
+#[function_component(UserDetail)]
+pub fn user_detail(props: &UserDetailProps) -> Html {
+    let id = props.id;
+    let user = use_state(|| None as Option<UserResponse>);
+    let user_state = use_state(|| ("".to_string(), "".to_string()));
+    let message = use_state(|| "".to_string());
+    let field_errors = use_state(HashMap::<String, String>::new);
+    // The user as someone else saved it while it was being edited here.
+    let conflict = use_state(|| None as Option<UserResponse>);
+    let navigator = use_navigator().unwrap();
+    let last_deleted = use_context::<LastDeleted>().expect("LastDeleted is provided by App");
+    // The backend enforces roles; this only hides what would be refused.
+    let me = use_context::<Session>().and_then(|session| (*session).clone());
+    let role = me.as_ref().map(|me| me.role);
+    let can_edit = role >= Some(Role::Editor);
+    let is_admin = role >= Some(Role::Admin);
+
+    {
+        let user = user.clone();
+        let user_state = user_state.clone();
+        let message = message.clone();
+        use_effect_with(id, move |&id| {
+            spawn_local(async move {
+                match api::client().get_user(id).await {
+                    Ok(fetched_user) => {
+                        user_state.set((fetched_user.name.clone(), fetched_user.email.clone()));
+                        user.set(Some(fetched_user));
+                    }
+
+                    Err(e) if e.status() == Some(404) => message.set("User not found".into()),
+
+                    Err(e) if e.status() == Some(401) => message.set("Log in to see users".into()),
+
+                    _ => message.set("Failed to fetch user".into()),
+                }
+            });
+        });
+    }
+
+    // Takes the version the edit is based on, which the backend checks. Only
+    // the fields edited here are sent, so other changes made meanwhile stay.
+    let update_user = {
+        let user = user.clone();
+        let user_state = user_state.clone();
+        let message = message.clone();
+        let field_errors = field_errors.clone();
+        let conflict = conflict.clone();
+
+        Callback::from(move |version: i32| {
+            let Some(loaded) = (*user).clone() else {
+                return;
+            };
+            let (name, email) = (*user_state).clone();
+            let user = user.clone();
+            let user_state = user_state.clone();
+            let message = message.clone();
+            let field_errors = field_errors.clone();
+            let conflict = conflict.clone();
+
+            let patch = UserPatch {
+                name: Some(name).filter(|name| name.trim() != loaded.name),
+                email: Some(email).filter(|email| email.trim() != loaded.email),
+            };
+            // Catch what the shared rules reject before making a round trip.
+            let patch = match patch.validated() {
+                Ok(patch) if patch.is_empty() => {
+                    message.set("Nothing to update".into());
+                    return;
+                }
+                Ok(patch) => patch,
+                Err(errors) => {
+                    message.set("Please fix the highlighted fields".into());
+                    field_errors.set(api::field_errors(errors));
+                    return;
+                }
+            };
+
+            spawn_local(async move {
+                match api::client().patch_user(id, &patch, version).await {
+                    Ok(updated_user) => {
+                        message.set("User updated successfully".into());
+                        field_errors.set(HashMap::new());
+                        user_state.set((updated_user.name.clone(), updated_user.email.clone()));
+                        user.set(Some(updated_user));
+                    }
+
+                    Err(ClientError::Api(problem)) if problem.status == 422 || problem.status == 409 => {
+                        message.set("Please fix the highlighted fields".into());
+                        field_errors.set(problem_field_errors(&problem));
+                    }
+
+                    Err(e) if e.status() == Some(412) =>
+                        match api::client().get_user(id).await {
+                            Ok(latest) => {
+                                message.set("Someone else changed this user while you were editing".into());
+                                conflict.set(Some(latest));
+                            }
+                            Err(_) => message.set("User was changed by someone else; reload the page".into()),
+                        }
+
+                    Err(e) if e.status() == Some(404) => message.set("User no longer exists".into()),
+
+                    Err(e) if e.status() == Some(401) => message.set("Log in to update users".into()),
+
+                    Err(e) if e.status() == Some(403) => message.set("Your role cannot update users".into()),
+
+                    _ => message.set("Failed to update user".into()),
+                }
+            });
+        })
+    };
+
+    let delete_user = {
+        let user = user.clone();
+        let message = message.clone();
+        let navigator = navigator.clone();
+
+        Callback::from(move |_| {
+            let Some(deleted) = (*user).clone() else {
+                return;
+            };
+            let user = user.clone();
+            let message = message.clone();
+            let navigator = navigator.clone();
+            let last_deleted = last_deleted.clone();
+
+            spawn_local(async move {
+                match api::client().delete_user(id, deleted.version).await {
+                    Ok(()) => {
+                        last_deleted.set(Some(deleted));
+                        navigator.push(&Route::Users);
+                    }
+
+                    Err(e) if e.status() == Some(412) => {
+                        message.set("User was changed by someone else; check it and delete again".into());
+                        if let Ok(latest) = api::client().get_user(id).await {
+                            user.set(Some(latest));
+                        }
+                    }
+
+                    Err(e) if e.status() == Some(404) => navigator.push(&Route::Users),
+
+                    Err(e) if e.status() == Some(401) => message.set("Log in to delete users".into()),
+
+                    Err(e) if e.status() == Some(403) => message.set("Only admins can delete users".into()),
+
+                    _ => message.set("Failed to delete user".into()),
+                }
+            });
+        })
+    };
+
+    let set_role = {
+        let user = user.clone();
+        let message = message.clone();
+
+        Callback::from(move |e: Event| {
+            let select = e.target_dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+            let (Some(role), Some(version)) = (Role::parse(&select.value()), user.as_ref().map(|u| u.version)) else {
+                return;
+            };
+            let user = user.clone();
+            let message = message.clone();
+
+            spawn_local(async move {
+                match api::client().set_role(id, role, version).await {
+                    Ok(updated_user) => {
+                        message.set(format!("Role changed to {}", role.as_str()));
+                        user.set(Some(updated_user));
+                    }
+
+                    Err(ClientError::Api(problem)) if problem.status == 422 =>
+                        message.set(problem.errors.first().map_or("Invalid role".into(), |e| e.message.clone())),
+
+                    Err(e) if e.status() == Some(412) => {
+                        message.set("User was changed by someone else; check it and change the role again".into());
+                        if let Ok(latest) = api::client().get_user(id).await {
+                            user.set(Some(latest));
+                        }
+                    }
+
+                    Err(e) if e.status() == Some(403) => message.set("Only admins can change roles".into()),
+
+                    _ => message.set("Failed to change role".into()),
+                }
+            });
+        })
+    };
+
+    // What the conflict dialog shows of an edit: only edited fields are sent.
+    let edited = |value: &str, field: fn(&UserResponse) -> &String| {
+        match &*user {
+            Some(loaded) if value.trim() == field(loaded) => "(unchanged)".to_string(),
+            _ => value.to_string(),
+        }
+    };
+
+    html! {
+        <div class="container mx-auto p-4">
+            <Link<Route> to={Route::Users} classes="text-blue-500">{ "← All users" }</Link<Route>>
+            <h1 class="text-4xl font-bold text-blue-500 mb-4">{ format!("User #{}", id) }</h1>
+
+            if let Some(current) = &*user {
+                <div class="mb-4">
+                    <input
+                        placeholder="Name"
+                        value={user_state.0.clone()}
+                        disabled={!can_edit}
+                        oninput={Callback::from({
+                            let user_state = user_state.clone();
+                            move |e: InputEvent| {
+                                let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                user_state.set((input.value(), user_state.1.clone()));
+                            }
+                        })}
+                        class="border rounded px-4 py-2 mr-2"
+                    />
+                    if let Some(error) = field_errors.get("name") {
+                        <p class="text-red-500 text-sm">{ error }</p>
+                    }
+                    <input
+                        placeholder="Email"
+                        value={user_state.1.clone()}
+                        disabled={!can_edit}
+                        oninput={Callback::from({
+                            let user_state = user_state.clone();
+                            move |e: InputEvent| {
+                                let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                user_state.set((user_state.0.clone(), input.value()));
+                            }
+                        })}
+                        class="border rounded px-4 py-2 mr-2"
+                    />
+                    if let Some(error) = field_errors.get("email") {
+                        <p class="text-red-500 text-sm">{ error }</p>
+                    }
+
+                    if can_edit {
+                        <button
+                            onclick={update_user.reform({
+                                let version = current.version;
+                                move |_| version
+                            })}
+                            class="bg-blue-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded"
+                        >
+                            { "Update User" }
+                        </button>
+                    }
+                    if is_admin {
+                        <button
+                            onclick={delete_user}
+                            class="ml-4 bg-red-500 hover:bg-red-700 text-white font-bold py-2 px-4 rounded"
+                        >
+                            { "Delete" }
+                        </button>
+                    }
+                </div>
+                <p class="mb-4 text-gray-500">
+                    <span title={format_time(current.created_at)}>
+                        { format!("Created {}", relative_time(current.created_at)) }
+                    </span>
+                    { " · " }
+                    <span title={format_time(current.updated_at)}>
+                        { format!("updated {}", relative_time(current.updated_at)) }
+                    </span>
+                </p>
+                <div class="mb-4 text-gray-700">
+                    { "Role: " }
+                    if is_admin {
+                        // Admins cannot demote themselves; the backend refuses it too.
+                        <select
+                            onchange={set_role}
+                            disabled={me.as_ref().map(|me| me.id) == Some(id)}
+                            class="border rounded px-2 py-1"
+                        >
+                            { for Role::ALL.iter().map(|role| html! {
+                                <option value={role.as_str()} selected={*role == current.role}>
+                                    { role.as_str() }
+                                </option>
+                            }) }
+                        </select>
+                    } else {
+                        { current.role.as_str() }
+                    }
+                </div>
+            }
+
+            if !message.is_empty() {
+                <p class="text-green-500 mt-2">{ &*message }</p>
+            }
+
+            if let Some(latest) = &*conflict {
+                <div class="mt-4 p-4 border rounded bg-yellow-50">
+                    <p class="font-semibold mb-2">{ "This user has changed since you opened it" }</p>
+                    <table class="table-auto mb-4">
+                        <thead>
+                            <tr>
+                                <th></th>
+                                <th class="text-left px-2 py-1">{ "Your changes" }</th>
+                                <th class="text-left px-2 py-1">{ "Saved by someone else" }</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            <tr>
+                                <td class="px-2 py-1 font-semibold">{ "Name" }</td>
+                                <td class="px-2 py-1">{ edited(&user_state.0, |u| &u.name) }</td>
+                                <td class="px-2 py-1">{ &latest.name }</td>
+                            </tr>
+                            <tr>
+                                <td class="px-2 py-1 font-semibold">{ "Email" }</td>
+                                <td class="px-2 py-1">{ edited(&user_state.1, |u| &u.email) }</td>
+                                <td class="px-2 py-1">{ &latest.email }</td>
+                            </tr>
+                        </tbody>
+                    </table>
+                    <button
+                        onclick={Callback::from({
+                            let conflict = conflict.clone();
+                            let update_user = update_user.clone();
+                            let version = latest.version;
+                            move |_| {
+                                conflict.set(None);
+                                update_user.emit(version);
+                            }
+                        })}
+                        class="bg-blue-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded"
+                    >
+                        { "Keep mine" }
+                    </button>
+                    <button
+                        onclick={Callback::from({
+                            let conflict = conflict.clone();
+                            let user = user.clone();
+                            let user_state = user_state.clone();
+                            let message = message.clone();
+                            let latest = latest.clone();
+                            move |_| {
+                                user_state.set((latest.name.clone(), latest.email.clone()));
+                                user.set(Some(latest.clone()));
+                                conflict.set(None);
+                                message.set("".into());
+                            }
+                        })}
+                        class="ml-4 bg-gray-500 hover:bg-gray-700 text-white font-bold py-2 px-4 rounded"
+                    >
+                        { "Use theirs" }
+                    </button>
+                </div>
+            }
+        </div>
+    }
+}
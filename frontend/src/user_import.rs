@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use yew::prelude::*;
+use yew_router::prelude::*;
+use wasm_bindgen_futures::{ JsFuture, spawn_local };
+use client::ClientError;
+use shared::{ ImportOptions, ImportReport, ImportRow, ImportStatus };
+
+use crate::Route;
+use crate::api::{ self, problem_field_errors };
+
+fn count(rows: &[ImportRow], status: ImportStatus) -> usize {
+    rows.iter().filter(|row| row.status == status).count()
+}
+
+fn rows_table(rows: &[ImportRow]) -> Html {
+    html! {
+        <table class="table-auto mb-4">
+            <thead>
+                <tr>
+                    <th class="text-left px-2 py-1">{ "Line" }</th>
+                    <th class="text-left px-2 py-1">{ "Name" }</th>
+                    <th class="text-left px-2 py-1">{ "Email" }</th>
+                    <th class="text-left px-2 py-1">{ "Status" }</th>
+                    <th class="text-left px-2 py-1">{ "Problems" }</th>
+                </tr>
+            </thead>
+            <tbody>
+                { for rows.iter().map(|row| {
+                    let (status, class) = match row.status {
+                        ImportStatus::Create => ("new", "text-green-600"),
+                        ImportStatus::Duplicate => ("duplicate", "text-yellow-600"),
+                        ImportStatus::Invalid => ("invalid", "text-red-500"),
+                    };
+                    html! {
+                        <tr>
+                            <td class="px-2 py-1 text-gray-500">{ row.line }</td>
+                            <td class="px-2 py-1">
+                                if let Some(user) = &row.user {
+                                    <Link<Route> to={Route::User { id: user.id }} classes="text-blue-500">
+                                        { &row.name }
+                                    </Link<Route>>
+                                } else {
+                                    { &row.name }
+                                }
+                            </td>
+                            <td class="px-2 py-1">{ &row.email }</td>
+                            <td class={classes!("px-2", "py-1", class)}>{ status }</td>
+                            <td class="px-2 py-1 text-sm text-red-500">
+                                { row.errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join("; ") }
+                            </td>
+                        </tr>
+                    }
+                }) }
+            </tbody>
+        </table>
+    }
+}
+
+/// Imports users from a CSV file in three steps: pick the file and its
+/// columns, check a dry run of it, then import the rows that passed.
+#[function_component(UserImport)]
+pub fn user_import() -> Html {
+    // Name and contents of the picked file.
+    let file = use_state(|| None as Option<(String, Vec<u8>)>);
+    let name_column = use_state(String::new);
+    let email_column = use_state(String::new);
+    let preview = use_state(|| None as Option<ImportReport>);
+    let imported = use_state(|| None as Option<ImportReport>);
+    let message = use_state(|| "".to_string());
+    let field_errors = use_state(HashMap::<String, String>::new);
+
+    let pick_file = {
+        let file = file.clone();
+        let preview = preview.clone();
+        Callback::from(move |e: Event| {
+            let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            let file = file.clone();
+            let preview = preview.clone();
+            let Some(picked) = input.files().and_then(|files| files.get(0)) else {
+                file.set(None);
+                return;
+            };
+            spawn_local(async move {
+                if let Ok(buffer) = JsFuture::from(picked.array_buffer()).await {
+                    file.set(Some((picked.name(), js_sys::Uint8Array::new(&buffer).to_vec())));
+                    preview.set(None);
+                }
+            });
+        })
+    };
+
+    let upload = {
+        let file = file.clone();
+        let name_column = name_column.clone();
+        let email_column = email_column.clone();
+        let preview = preview.clone();
+        let imported = imported.clone();
+        let message = message.clone();
+        let field_errors = field_errors.clone();
+        Callback::from(move |dry_run: bool| {
+            let Some((file_name, csv)) = (*file).clone() else {
+                message.set("Choose a CSV file first".into());
+                return;
+            };
+            let column = |column: &str| Some(column.trim().to_string()).filter(|column| !column.is_empty());
+            let options = ImportOptions {
+                name_column: column(&name_column),
+                email_column: column(&email_column),
+                dry_run,
+            };
+            let preview = preview.clone();
+            let imported = imported.clone();
+            let message = message.clone();
+            let field_errors = field_errors.clone();
+
+            spawn_local(async move {
+                match api::client().import_users(&file_name, csv, &options).await {
+                    Ok(report) => {
+                        message.set("".into());
+                        field_errors.set(HashMap::new());
+                        if report.dry_run {
+                            preview.set(Some(report));
+                        } else {
+                            preview.set(None);
+                            imported.set(Some(report));
+                        }
+                    }
+
+                    Err(ClientError::Api(problem)) if problem.status == 422 => {
+                        preview.set(None);
+                        message.set("Please fix the highlighted fields".into());
+                        field_errors.set(problem_field_errors(&problem));
+                    }
+
+                    Err(e) if e.status() == Some(413) => message.set("The file is too large".into()),
+
+                    Err(e) if e.status() == Some(401) => message.set("Log in to import users".into()),
+
+                    Err(e) if e.status() == Some(403) => message.set("Your role cannot create users".into()),
+
+                    _ => message.set("Failed to import users".into()),
+                }
+            });
+        })
+    };
+
+    let start_over = {
+        let file = file.clone();
+        let preview = preview.clone();
+        let imported = imported.clone();
+        Callback::from(move |_| {
+            file.set(None);
+            preview.set(None);
+            imported.set(None);
+        })
+    };
+
+    let column_input = |label: &'static str, field: &'static str, default: &'static str, value: &UseStateHandle<String>| {
+        let value = value.clone();
+        html! {
+            <div class="mb-2">
+                <label class="text-gray-700 mr-2">{ label }</label>
+                <input
+                    placeholder={default}
+                    value={(*value).clone()}
+                    oninput={Callback::from({
+                        let value = value.clone();
+                        move |e: InputEvent| {
+                            let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                            value.set(input.value());
+                        }
+                    })}
+                    class="border rounded px-4 py-2"
+                />
+                if let Some(error) = field_errors.get(field) {
+                    <p class="text-red-500 text-sm">{ error }</p>
+                }
+            </div>
+        }
+    };
+
+    html! {
+        <div class="container mx-auto p-4">
+            <h1 class="text-4xl font-bold text-blue-500 mb-4">{ "Import Users" }</h1>
+
+            if !message.is_empty() {
+                <p class="text-red-500 mb-4">{ &*message }</p>
+            }
+
+            if let Some(report) = &*imported {
+                <p class="text-green-500 mb-4">
+                    { format!("Imported {} of {} users", report.created, report.rows.len()) }
+                </p>
+                if report.created < report.rows.len() {
+                    <h2 class="text-2xl font-bold text-gray-700 mb-2">{ "Skipped rows" }</h2>
+                    { rows_table(&report.rows.iter().filter(|row| row.user.is_none()).cloned().collect::<Vec<_>>()) }
+                }
+                <button
+                    onclick={start_over.clone()}
+                    class="bg-gray-500 hover:bg-gray-700 text-white font-bold py-2 px-4 rounded mr-2"
+                >
+                    { "Import another file" }
+                </button>
+                <Link<Route> to={Route::Users} classes="text-blue-500">{ "Back to users" }</Link<Route>>
+            } else if let Some(report) = &*preview {
+                <p class="text-gray-700 mb-2">
+                    { format!(
+                        "Names from column \"{}\", emails from column \"{}\"",
+                        report.name_column,
+                        report.email_column
+                    ) }
+                </p>
+                <p class="text-gray-700 mb-4">
+                    { format!(
+                        "{} new, {} duplicates, {} invalid",
+                        count(&report.rows, ImportStatus::Create),
+                        count(&report.rows, ImportStatus::Duplicate),
+                        count(&report.rows, ImportStatus::Invalid)
+                    ) }
+                </p>
+                { rows_table(&report.rows) }
+                <button
+                    onclick={Callback::from({
+                        let preview = preview.clone();
+                        move |_| preview.set(None)
+                    })}
+                    class="bg-gray-500 hover:bg-gray-700 text-white font-bold py-2 px-4 rounded mr-2"
+                >
+                    { "Back" }
+                </button>
+                <button
+                    disabled={count(&report.rows, ImportStatus::Create) == 0}
+                    onclick={upload.reform(|_| false)}
+                    class="bg-blue-500 hover:bg-blue-700 disabled:opacity-50 text-white font-bold py-2 px-4 rounded"
+                >
+                    { format!("Import {} users", count(&report.rows, ImportStatus::Create)) }
+                </button>
+            } else {
+                <p class="text-gray-700 mb-4">
+                    { "A CSV file with a header row; other columns are ignored. Nothing is saved before the preview is confirmed." }
+                </p>
+                <div class="mb-2">
+                    <input type="file" accept=".csv,text/csv" onchange={pick_file} />
+                    if let Some((file_name, _)) = &*file {
+                        <span class="text-gray-700">{ file_name }</span>
+                    }
+                    if let Some(error) = field_errors.get("file") {
+                        <p class="text-red-500 text-sm">{ error }</p>
+                    }
+                </div>
+                { column_input("Name column", "name_column", "name", &name_column) }
+                { column_input("Email column", "email_column", "email", &email_column) }
+                <button
+                    disabled={file.is_none()}
+                    onclick={upload.reform(|_| true)}
+                    class="bg-blue-500 hover:bg-blue-700 disabled:opacity-50 text-white font-bold py-2 px-4 rounded mr-2"
+                >
+                    { "Preview" }
+                </button>
+                <Link<Route> to={Route::Users} classes="text-blue-500">{ "Back to users" }</Link<Route>>
+            }
+        </div>
+    }
+}
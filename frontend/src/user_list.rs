@@ -0,0 +1,593 @@
+use std::collections::{ HashMap, HashSet };
+
+use yew::prelude::*;
+use yew_router::prelude::*;
+use gloo::timers::callback::Timeout;
+use wasm_bindgen_futures::spawn_local;
+use client::ClientError;
+use shared::{ BulkMode, BulkOperation, BulkRequest, CreateUser, ListUsers, Page, Role, SearchHit, UserResponse };
+
+use crate::{ Route, Session };
+use crate::api::{ self, problem_field_errors };
+use crate::time::{ format_time, relative_time };
+use crate::toast::LastDeleted;
+
+const PAGE_SIZE: i64 = 20;
+const SUGGESTION_LIMIT: i64 = 5;
+// Wait for a pause in typing before asking the backend for suggestions.
+const SUGGESTION_DEBOUNCE_MS: u32 = 250;
+
+/// Renders a search snippet, turning the backend's `<b>` markers into
+/// `<mark>` elements while keeping everything else as plain text.
+fn highlight(snippet: &str) -> Html {
+    let mut parts = Vec::new();
+    for (i, segment) in snippet.split("<b>").enumerate() {
+        match segment.split_once("</b>") {
+            Some((marked, rest)) if i > 0 => {
+                parts.push(html! { <mark>{ marked }</mark> });
+                parts.push(html! { { rest } });
+            }
+            _ => parts.push(html! { { segment } }),
+        }
+    }
+    parts.into_iter().collect()
+}
+
+fn filter_query(search: &str, sort: &str, show_deleted: bool) -> ListUsers {
+    ListUsers {
+        q: Some(search.trim().to_string()).filter(|q| !q.is_empty()),
+        sort: Some(sort.to_string()),
+        include_deleted: Some(true).filter(|_| show_deleted),
+        limit: Some(PAGE_SIZE),
+        ..ListUsers::default()
+    }
+}
+
+#[function_component(UserList)]
+pub fn user_list() -> Html {
+    let user_state = use_state(|| ("".to_string(), "".to_string()));
+    let message = use_state(|| "".to_string());
+    let field_errors = use_state(HashMap::<String, String>::new);
+    let page = use_state(|| None as Option<Page<UserResponse>>);
+    let search = use_state(String::new);
+    let suggestions = use_state(Vec::<SearchHit>::new);
+    let suggestion_timer = use_mut_ref(|| None as Option<Timeout>);
+    let sort = use_state(|| "id".to_string());
+    let show_deleted = use_state(|| false);
+    // Ids picked for bulk delete; only ever from the page on screen.
+    let selected = use_state(HashSet::<i32>::new);
+    // Query of the page on screen, so mutations can refresh it in place.
+    let current_query = use_mut_ref(|| filter_query("", "id", false));
+    let last_deleted = use_context::<LastDeleted>().expect("LastDeleted is provided by App");
+    // The backend enforces roles; this only hides what would be refused.
+    let role = use_context::<Session>().and_then(|session| session.as_ref().map(|user| user.role));
+    let can_edit = role >= Some(Role::Editor);
+    let can_delete = role >= Some(Role::Admin);
+
+    let get_users = {
+        let page = page.clone();
+        let message = message.clone();
+        let selected = selected.clone();
+        let current_query = current_query.clone();
+        Callback::from(move |query: ListUsers| {
+            let page = page.clone();
+            let message = message.clone();
+            let selected = selected.clone();
+            *current_query.borrow_mut() = query.clone();
+            spawn_local(async move {
+                match api::client().list_users(&query).await {
+                    Ok(fetched_page) => {
+                        selected.set(HashSet::new());
+                        page.set(Some(fetched_page));
+                    }
+                    Err(e) if e.status() == Some(401) => message.set("Log in to see users".into()),
+                    Err(_) => message.set("Failed to fetch users".into()),
+                }
+            });
+        })
+    };
+
+    let refresh = {
+        let get_users = get_users.clone();
+        let current_query = current_query.clone();
+        Callback::from(move |_| get_users.emit(current_query.borrow().clone()))
+    };
+
+    let suggest = {
+        let suggestions = suggestions.clone();
+        Callback::from(move |q: String| {
+            let suggestions = suggestions.clone();
+            // Dropping the pending timeout cancels it.
+            *suggestion_timer.borrow_mut() = None;
+            if q.trim().is_empty() {
+                suggestions.set(Vec::new());
+                return;
+            }
+
+            *suggestion_timer.borrow_mut() = Some(
+                Timeout::new(SUGGESTION_DEBOUNCE_MS, move || {
+                    spawn_local(async move {
+                        if let Ok(hits) = api::client().search_users(q.trim(), SUGGESTION_LIMIT).await {
+                            suggestions.set(hits);
+                        }
+                    });
+                })
+            );
+        })
+    };
+
+    let sort_by = {
+        let get_users = get_users.clone();
+        let search = search.clone();
+        let sort = sort.clone();
+        let show_deleted = show_deleted.clone();
+        Callback::from(move |column: &'static str| {
+            let new_sort = if *sort == column { format!("-{}", column) } else { column.to_string() };
+            get_users.emit(filter_query(&search, &new_sort, *show_deleted));
+            sort.set(new_sort);
+        })
+    };
+
+    let sort_header = |label: &'static str, column: &'static str| {
+        let arrow = if *sort == column {
+            " ▲"
+        } else if sort.strip_prefix('-') == Some(column) {
+            " ▼"
+        } else {
+            ""
+        };
+        html! {
+            <th
+                onclick={sort_by.reform(move |_| column)}
+                class="text-left px-2 py-1 cursor-pointer select-none hover:text-blue-500"
+            >
+                { format!("{}{}", label, arrow) }
+            </th>
+        }
+    };
+
+    let create_user = {
+        let user_state = user_state.clone();
+        let message = message.clone();
+        let field_errors = field_errors.clone();
+        let refresh = refresh.clone();
+        Callback::from(move |_| {
+            let (name, email) = (*user_state).clone();
+            let user_state = user_state.clone();
+            let message = message.clone();
+            let field_errors = field_errors.clone();
+            let refresh = refresh.clone();
+
+            // Catch what the shared rules reject before making a round trip.
+            let user_data = match (CreateUser { name, email }).validated() {
+                Ok(user_data) => user_data,
+                Err(errors) => {
+                    message.set("Please fix the highlighted fields".into());
+                    field_errors.set(api::field_errors(errors));
+                    return;
+                }
+            };
+
+            spawn_local(async move {
+                match api::client().create_user(&user_data).await {
+                    Ok(_) => {
+                        message.set("User created successfully".into());
+                        field_errors.set(HashMap::new());
+                        user_state.set(("".to_string(), "".to_string()));
+                        refresh.emit(());
+                    }
+
+                    Err(ClientError::Api(problem)) if problem.status == 422 || problem.status == 409 => {
+                        message.set("Please fix the highlighted fields".into());
+                        field_errors.set(problem_field_errors(&problem));
+                    }
+
+                    Err(e) if e.status() == Some(401) => message.set("Log in to create users".into()),
+
+                    Err(e) if e.status() == Some(403) => message.set("Your role cannot create users".into()),
+
+                    _ => message.set("Failed to create user".into()),
+                }
+            });
+        })
+    };
+
+    let delete_user = {
+        let message = message.clone();
+        let refresh = refresh.clone();
+
+        Callback::from(move |user: UserResponse| {
+            let message = message.clone();
+            let refresh = refresh.clone();
+            let last_deleted = last_deleted.clone();
+
+            spawn_local(async move {
+                match api::client().delete_user(user.id, user.version).await {
+                    Ok(()) => {
+                        message.set("".into());
+                        last_deleted.set(Some(user));
+                        refresh.emit(());
+                    }
+
+                    Err(e) if e.status() == Some(404) => {
+                        message.set("User no longer exists".into());
+                        refresh.emit(());
+                    }
+
+                    Err(e) if e.status() == Some(412) => {
+                        message.set("User was changed by someone else; check it and delete again".into());
+                        refresh.emit(());
+                    }
+
+                    Err(e) if e.status() == Some(401) => message.set("Log in to delete users".into()),
+
+                    Err(e) if e.status() == Some(403) => message.set("Only admins can delete users".into()),
+
+                    _ => message.set("Failed to delete user".into()),
+                }
+            });
+        })
+    };
+
+    let restore_user = {
+        let message = message.clone();
+        let refresh = refresh.clone();
+
+        Callback::from(move |id: i32| {
+            let message = message.clone();
+            let refresh = refresh.clone();
+
+            spawn_local(async move {
+                match api::client().restore_user(id).await {
+                    Ok(_) => {
+                        message.set("User restored successfully".into());
+                        refresh.emit(());
+                    }
+
+                    Err(e) if e.status() == Some(409) => message.set("Another user has taken the email since".into()),
+
+                    Err(e) if e.status() == Some(404) => {
+                        message.set("User was already purged".into());
+                        refresh.emit(());
+                    }
+
+                    Err(e) if e.status() == Some(403) => message.set("Only admins can restore users".into()),
+
+                    _ => message.set("Failed to restore user".into()),
+                }
+            });
+        })
+    };
+
+    let delete_selected = {
+        let message = message.clone();
+        let refresh = refresh.clone();
+
+        Callback::from(move |users: Vec<UserResponse>| {
+            let message = message.clone();
+            let refresh = refresh.clone();
+            let operations = users
+                .iter()
+                .map(|user| BulkOperation::Delete { id: user.id, version: user.version })
+                .collect();
+            // One stale or vanished user should not keep the rest from going.
+            let request = BulkRequest { operations, mode: BulkMode::BestEffort };
+
+            spawn_local(async move {
+                match api::client().bulk_users(&request).await {
+                    Ok(response) => {
+                        let deleted = response.results.iter().filter(|result| result.error.is_none()).count();
+                        if deleted == users.len() {
+                            message.set(format!("Deleted {} users", deleted));
+                        } else {
+                            message.set(format!(
+                                "Deleted {} of {} users; the rest were changed or removed by someone else",
+                                deleted,
+                                users.len()
+                            ));
+                        }
+                        refresh.emit(());
+                    }
+
+                    Err(e) if e.status() == Some(401) => message.set("Log in to delete users".into()),
+
+                    Err(e) if e.status() == Some(403) => message.set("Only admins can delete users".into()),
+
+                    _ => message.set("Failed to delete users".into()),
+                }
+            });
+        })
+    };
+
+    // Deleted users cannot be deleted again, so they are never selectable.
+    let selectable: Vec<UserResponse> = page
+        .iter()
+        .flat_map(|page| page.items.iter())
+        .filter(|user| user.deleted_at.is_none())
+        .cloned()
+        .collect();
+    let all_selected = !selectable.is_empty() && selectable.iter().all(|user| selected.contains(&user.id));
+
+    html! {
+        <div class="container mx-auto p-4">
+            <h1 class="text-4xl font-bold text-blue-500 mb-4">{ "User Management" }</h1>
+                if can_edit {
+                    <div class="mb-4">
+                        <input
+                            placeholder="Name"
+                            value={user_state.0.clone()}
+                            oninput={Callback::from({
+                                let user_state = user_state.clone();
+                                move |e: InputEvent| {
+                                    let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                    user_state.set((input.value(), user_state.1.clone()));
+                                }
+                            })}
+                            class="border rounded px-4 py-2 mr-2"
+                        />
+                        if let Some(error) = field_errors.get("name") {
+                            <p class="text-red-500 text-sm">{ error }</p>
+                        }
+                        <input
+                            placeholder="Email"
+                            value={user_state.1.clone()}
+                            oninput={Callback::from({
+                                let user_state = user_state.clone();
+                                move |e: InputEvent| {
+                                    let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                    user_state.set((user_state.0.clone(), input.value()));
+                                }
+                            })}
+                            class="border rounded px-4 py-2 mr-2"
+                        />
+                        if let Some(error) = field_errors.get("email") {
+                            <p class="text-red-500 text-sm">{ error }</p>
+                        }
+
+                        <button
+                            onclick={create_user.clone()}
+                            class="bg-blue-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded"
+                        >
+                            { "Create User" }
+                        </button>
+                        <Link<Route>
+                            to={Route::Import}
+                            classes="ml-2 bg-gray-500 hover:bg-gray-700 text-white font-bold py-2 px-4 rounded"
+                        >
+                            { "Import CSV" }
+                        </Link<Route>>
+                    </div>
+                }
+                if !message.is_empty() {
+                    <p class="text-green-500 mb-4">{ &*message }</p>
+                }
+
+                <div class="mb-4">
+                    <input
+                        placeholder="Search name or email"
+                        value={(*search).clone()}
+                        oninput={Callback::from({
+                            let search = search.clone();
+                            let suggest = suggest.clone();
+                            move |e: InputEvent| {
+                                let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                suggest.emit(input.value());
+                                search.set(input.value());
+                            }
+                        })}
+                        onkeypress={Callback::from({
+                            let get_users = get_users.clone();
+                            let search = search.clone();
+                            let sort = sort.clone();
+                            let show_deleted = show_deleted.clone();
+                            let suggestions = suggestions.clone();
+                            move |e: KeyboardEvent| {
+                                if e.key() == "Enter" {
+                                    suggestions.set(Vec::new());
+                                    get_users.emit(filter_query(&search, &sort, *show_deleted));
+                                }
+                            }
+                        })}
+                        class="border rounded px-4 py-2 mr-2"
+                    />
+                    <button
+                        onclick={get_users.reform({
+                            let search = search.clone();
+                            let sort = sort.clone();
+                            let show_deleted = show_deleted.clone();
+                            move |_| filter_query(&search, &sort, *show_deleted)
+                        })}
+                        class="bg-gray-500 hover:bg-gray-700 text-white font-bold py-2 px-4 rounded"
+                    >
+                        { "Fetch User List" }
+                    </button>
+                    if can_delete {
+                        <label class="ml-4 text-gray-700">
+                            <input
+                                type="checkbox"
+                                checked={*show_deleted}
+                                onchange={Callback::from({
+                                    let get_users = get_users.clone();
+                                    let search = search.clone();
+                                    let sort = sort.clone();
+                                    let show_deleted = show_deleted.clone();
+                                    move |e: Event| {
+                                        let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                        get_users.emit(filter_query(&search, &sort, input.checked()));
+                                        show_deleted.set(input.checked());
+                                    }
+                                })}
+                                class="mr-1"
+                            />
+                            { "Show deleted" }
+                        </label>
+                    }
+                    if !suggestions.is_empty() {
+                        <ul class="border rounded mt-1 w-96 bg-white shadow">
+                            { for suggestions.iter().map(|hit| html! {
+                                <li class="px-4 py-1 hover:bg-gray-100">
+                                    <Link<Route> to={Route::User { id: hit.user.id }}>
+                                        <span class="font-semibold">{ highlight(&hit.name_snippet) }</span>
+                                        <span class="ml-2 text-gray-500">{ highlight(&hit.email_snippet) }</span>
+                                    </Link<Route>>
+                                </li>
+                            }) }
+                        </ul>
+                    }
+                </div>
+
+                <h2 class="text-2xl font-bold text-gray-700 mb-2">{ "User List" }</h2>
+
+                if can_delete && !selected.is_empty() {
+                    <div class="mb-2">
+                        <button
+                            onclick={delete_selected.reform({
+                                let users: Vec<UserResponse> = selectable
+                                    .iter()
+                                    .filter(|user| selected.contains(&user.id))
+                                    .cloned()
+                                    .collect();
+                                move |_| users.clone()
+                            })}
+                            class="bg-red-500 hover:bg-red-700 text-white font-bold py-1 px-2 rounded"
+                        >
+                            { format!("Delete selected ({})", selected.len()) }
+                        </button>
+                    </div>
+                }
+
+                <table class="table-auto">
+                    <thead>
+                        <tr>
+                            if can_delete {
+                                <th class="px-2 py-1">
+                                    <input
+                                        type="checkbox"
+                                        title="Select all on this page"
+                                        checked={all_selected}
+                                        onchange={Callback::from({
+                                            let selected = selected.clone();
+                                            let ids: HashSet<i32> = selectable.iter().map(|user| user.id).collect();
+                                            move |e: Event| {
+                                                let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                                selected.set(if input.checked() { ids.clone() } else { HashSet::new() });
+                                            }
+                                        })}
+                                    />
+                                </th>
+                            }
+                            { sort_header("ID", "id") }
+                            { sort_header("Name", "name") }
+                            { sort_header("Email", "email") }
+                            <th class="text-left px-2 py-1">{ "Role" }</th>
+                            { sort_header("Created", "created_at") }
+                            { sort_header("Updated", "updated_at") }
+                            <th></th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for page.iter().flat_map(|page| page.items.iter()).map(|user| {
+                            let user_id = user.id;
+                            html! {
+                                <tr class={classes!(user.deleted_at.is_some().then_some("text-gray-400"))}>
+                                    if can_delete {
+                                        <td class="px-2 py-1">
+                                            if user.deleted_at.is_none() {
+                                                <input
+                                                    type="checkbox"
+                                                    checked={selected.contains(&user_id)}
+                                                    onchange={Callback::from({
+                                                        let selected = selected.clone();
+                                                        move |e: Event| {
+                                                            let input = e.target_dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                                            let mut ids = (*selected).clone();
+                                                            if input.checked() {
+                                                                ids.insert(user_id);
+                                                            } else {
+                                                                ids.remove(&user_id);
+                                                            }
+                                                            selected.set(ids);
+                                                        }
+                                                    })}
+                                                />
+                                            }
+                                        </td>
+                                    }
+                                    <td class="px-2 py-1">{ user.id }</td>
+                                    <td class="px-2 py-1 font-semibold">{ &user.name }</td>
+                                    <td class="px-2 py-1">{ &user.email }</td>
+                                    <td class="px-2 py-1 text-gray-500">{ user.role.as_str() }</td>
+                                    <td class="px-2 py-1 text-gray-500" title={format_time(user.created_at)}>
+                                        { relative_time(user.created_at) }
+                                    </td>
+                                    <td class="px-2 py-1 text-gray-500" title={format_time(user.updated_at)}>
+                                        { relative_time(user.updated_at) }
+                                    </td>
+                                    <td class="px-2 py-1">
+                                        if let Some(deleted_at) = user.deleted_at {
+                                            { format!("deleted {}", format_time(deleted_at)) }
+                                            if can_delete {
+                                                <button
+                                                    onclick={restore_user.reform(move |_| user_id)}
+                                                    class="ml-4 bg-green-500 hover:bg-green-700 text-white font-bold py-1 px-2 rounded"
+                                                >
+                                                    { "Restore" }
+                                                </button>
+                                            }
+                                        } else {
+                                            if can_delete {
+                                                <button
+                                                    onclick={delete_user.reform({
+                                                        let user = user.clone();
+                                                        move |_| user.clone()
+                                                    })}
+                                                    class="ml-4 bg-red-500 hover:bg-red-700 text-white font-bold py-1 px-2 rounded"
+                                                >
+                                                    { "Delete" }
+                                                </button>
+                                            }
+                                            <Link<Route>
+                                                to={Route::User { id: user_id }}
+                                                classes="ml-4 bg-yellow-500 hover:bg-yellow-700 text-white font-bold py-1 px-2 rounded"
+                                            >
+                                                { if can_edit { "Edit" } else { "View" } }
+                                            </Link<Route>>
+                                        }
+                                    </td>
+                                </tr>
+                            }
+                        })}
+                    </tbody>
+                </table>
+
+                if let Some(page) = &*page {
+                    <div class="mt-4 flex items-center">
+                        <button
+                            disabled={page.prev_cursor.is_none()}
+                            onclick={get_users.reform({
+                                let current_query = current_query.clone();
+                                let before = page.prev_cursor.clone();
+                                move |_| ListUsers { after: None, before: before.clone(), ..current_query.borrow().clone() }
+                            })}
+                            class="bg-gray-300 hover:bg-gray-400 disabled:opacity-50 font-bold py-1 px-3 rounded"
+                        >
+                            { "Previous" }
+                        </button>
+                        <span class="mx-4 text-gray-700">{ format!("{} users", page.total) }</span>
+                        <button
+                            disabled={page.next_cursor.is_none()}
+                            onclick={get_users.reform({
+                                let current_query = current_query.clone();
+                                let after = page.next_cursor.clone();
+                                move |_| ListUsers { after: after.clone(), before: None, ..current_query.borrow().clone() }
+                            })}
+                            class="bg-gray-300 hover:bg-gray-400 disabled:opacity-50 font-bold py-1 px-3 rounded"
+                        >
+                            { "Next" }
+                        </button>
+                    </div>
+                }
+
+        </div>
+    }
+}
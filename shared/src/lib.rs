@@ -0,0 +1,656 @@
+//! Request and response bodies of the `/api` endpoints, shared by the backend
+//! and the frontend so that a change on one side fails to compile on the
+//! other instead of failing at runtime.
+
+use serde::{ Deserialize, Deserializer, Serialize };
+
+pub mod validation;
+
+/// Stable values of [`Problem::code`].
+pub mod codes {
+    pub const BAD_REQUEST: &str = "bad_request";
+    pub const CONFLICT: &str = "conflict";
+    pub const EMAIL_TAKEN: &str = "email_taken";
+    pub const FORBIDDEN: &str = "forbidden";
+    pub const INTERNAL_ERROR: &str = "internal_error";
+    pub const INVALID_CREDENTIALS: &str = "invalid_credentials";
+    pub const INVALID_GRANT: &str = "invalid_grant";
+    pub const MALFORMED_BODY: &str = "malformed_body";
+    pub const NOT_FOUND: &str = "not_found";
+    pub const PAYLOAD_TOO_LARGE: &str = "payload_too_large";
+    pub const PRECONDITION_FAILED: &str = "precondition_failed";
+    pub const PRECONDITION_REQUIRED: &str = "precondition_required";
+    pub const REQUEST_FAILED: &str = "request_failed";
+    pub const ROLLED_BACK: &str = "rolled_back";
+    pub const SERVICE_UNAVAILABLE: &str = "service_unavailable";
+    pub const UNAUTHORIZED: &str = "unauthorized";
+    pub const UNSUPPORTED_MEDIA_TYPE: &str = "unsupported_media_type";
+    pub const VALIDATION_FAILED: &str = "validation_failed";
+}
+
+/// What a user may do, in increasing order of privilege; each role includes
+/// everything the previous ones can do.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can list, search and view users.
+    Viewer,
+    /// Can also create and update users.
+    Editor,
+    /// Can also delete users and change roles.
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Viewer, Role::Editor, Role::Admin];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Role> {
+        Role::ALL.into_iter().find(|role| role.as_str() == value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UserResponse {
+    pub id: i32,
+    pub name: String,
+    pub email: String,
+    pub role: Role,
+    /// Incremented by every change. Updates and deletes must send it back as
+    /// `If-Match: "<version>"`, the user's `ETag`.
+    pub version: i32,
+    /// Unix timestamps, kept by the database.
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// Unix timestamp of the deletion, for users listed with
+    /// `include_deleted=true`. Deleted users can be restored until purged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<i64>,
+}
+
+/// Body of `POST /api/users`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateUser {
+    pub name: String,
+    pub email: String,
+}
+
+/// Body of `PUT /api/users/<id>`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UpdateUser {
+    pub name: String,
+    pub email: String,
+}
+
+/// Body of `PATCH /api/users/<id>`, an RFC 7396 JSON Merge Patch sent as
+/// `application/merge-patch+json`: only the fields present change. Neither
+/// can be removed, so `null` is rejected like unknown fields are.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(deny_unknown_fields)]
+pub struct UserPatch {
+    #[serde(default, deserialize_with = "not_null", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "not_null", skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+/// Deserializes a field that may be left out but not set to `null`.
+fn not_null<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Body of `PUT /api/users/<id>/role`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SetRole {
+    pub role: Role,
+}
+
+impl CreateUser {
+    /// Trims the fields and checks them, returning every failing field.
+    pub fn validated(self) -> Result<CreateUser, Vec<FieldError>> {
+        let (name, email) = validation::validate_user(&self.name, &self.email)?;
+        Ok(CreateUser { name, email })
+    }
+}
+
+impl UpdateUser {
+    /// Trims the fields and checks them, returning every failing field.
+    pub fn validated(self) -> Result<UpdateUser, Vec<FieldError>> {
+        let (name, email) = validation::validate_user(&self.name, &self.email)?;
+        Ok(UpdateUser { name, email })
+    }
+}
+
+impl UserPatch {
+    /// Trims the fields present and checks them, returning every failing field.
+    pub fn validated(self) -> Result<UserPatch, Vec<FieldError>> {
+        let name = self.name.map(|name| name.trim().to_string());
+        let email = self.email.map(|email| email.trim().to_string());
+
+        let mut errors = Vec::new();
+        if let Some(name) = &name {
+            validation::validate_name(name, &mut errors);
+        }
+        if let Some(email) = &email {
+            validation::validate_email(email, &mut errors);
+        }
+
+        if errors.is_empty() { Ok(UserPatch { name, email }) } else { Err(errors) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.email.is_none()
+    }
+}
+
+/// How `POST /api/users/bulk` treats operations that fail.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BulkMode {
+    /// Nothing is saved unless every operation succeeds.
+    #[default]
+    Atomic,
+    /// The operations that succeed are saved even if others fail.
+    BestEffort,
+}
+
+/// One operation of `POST /api/users/bulk`. Updates and deletes carry the
+/// version they are based on, like `If-Match` on the single-user routes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Create {
+        name: String,
+        email: String,
+    },
+    Update {
+        id: i32,
+        version: i32,
+        changes: UserPatch,
+    },
+    Delete {
+        id: i32,
+        version: i32,
+    },
+}
+
+/// Body of `POST /api/users/bulk`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkRequest {
+    pub operations: Vec<BulkOperation>,
+    #[serde(default)]
+    pub mode: BulkMode,
+}
+
+impl BulkRequest {
+    /// Checks the size of the batch; each operation is validated as it runs.
+    pub fn validated(self) -> Result<BulkRequest, Vec<FieldError>> {
+        if self.operations.len() > validation::BULK_MAX_OPERATIONS {
+            return Err(
+                vec![
+                    FieldError::new(
+                        "operations",
+                        "too_many",
+                        format!("At most {} operations can be sent at once", validation::BULK_MAX_OPERATIONS)
+                    )
+                ]
+            );
+        }
+        Ok(self)
+    }
+}
+
+/// Outcome of one bulk operation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkResult {
+    /// What the single-user route would have answered, e.g. 201, 204 or 409.
+    /// 424 marks an operation undone because another one failed.
+    pub status: u16,
+    /// The user as created or updated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Problem>,
+}
+
+/// Response of `POST /api/users/bulk`, with a result per operation in the
+/// order they were sent.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkResponse {
+    /// Whether anything was saved: false only when an atomic batch failed.
+    pub committed: bool,
+    pub results: Vec<BulkResult>,
+}
+
+/// Form fields of `POST /api/users/import` besides the CSV `file`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ImportOptions {
+    /// Header of the column holding names; `name` when left out.
+    pub name_column: Option<String>,
+    /// Header of the column holding emails; `email` when left out.
+    pub email_column: Option<String>,
+    /// Checks the file and reports what would change without saving it.
+    pub dry_run: bool,
+}
+
+/// What became of one row of an import.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    /// Created, or would be on a dry run.
+    Create,
+    /// Skipped: the email is in use or on an earlier row.
+    Duplicate,
+    /// Skipped: a field fails validation.
+    Invalid,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportRow {
+    /// Line of the file the row starts on; the header is line 1.
+    pub line: u64,
+    pub name: String,
+    pub email: String,
+    pub status: ImportStatus,
+    /// Why a row was skipped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// The created user; never set on a dry run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserResponse>,
+}
+
+/// Response of `POST /api/users/import`, with a row per data row of the file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Headers the names and emails were read from.
+    pub name_column: String,
+    pub email_column: String,
+    /// How many users were created; 0 on a dry run.
+    pub created: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+/// Body of `POST /api/auth/login`. Passwords are never trimmed, and the type
+/// is not `Debug` so that it cannot end up in logs.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Login {
+    pub email: String,
+    pub password: String,
+}
+
+/// Body of `POST /api/auth/token`, modelled on the OAuth 2.0 password and
+/// refresh token grants.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "grant_type", rename_all = "snake_case")]
+pub enum TokenRequest {
+    Password {
+        email: String,
+        password: String,
+    },
+    RefreshToken {
+        refresh_token: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TokenResponse {
+    /// JWT to send as `Authorization: Bearer <token>`.
+    pub access_token: String,
+    /// Always `Bearer`.
+    pub token_type: String,
+    /// Seconds until `access_token` expires.
+    pub expires_in: u64,
+    /// Single-use token for `grant_type=refresh_token`.
+    pub refresh_token: String,
+}
+
+/// Body of `POST /api/auth/revoke`.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RevokeToken {
+    pub refresh_token: String,
+}
+
+/// What an API key may do. Keys never get more than an editor: deleting
+/// users and managing roles or keys takes a logged-in admin.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Scope {
+    /// List, search and view users.
+    #[serde(rename = "users:read")]
+    UsersRead,
+    /// Create and update users.
+    #[serde(rename = "users:write")]
+    UsersWrite,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 2] = [Scope::UsersRead, Scope::UsersWrite];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::UsersRead => "users:read",
+            Scope::UsersWrite => "users:write",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Scope> {
+        Scope::ALL.into_iter().find(|scope| scope.as_str() == value)
+    }
+}
+
+/// Body of `POST /api/keys`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateApiKey {
+    /// What the key is for, e.g. the client using it.
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Days until the key stops working; never, if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_in_days: Option<u32>,
+}
+
+impl CreateApiKey {
+    /// Trims the name, drops repeated scopes and checks the rest, returning
+    /// every failing field.
+    pub fn validated(self) -> Result<CreateApiKey, Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let name = self.name.trim().to_string();
+        validation::validate_name(&name, &mut errors);
+
+        let mut scopes = Vec::new();
+        for scope in self.scopes {
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+        if scopes.is_empty() {
+            errors.push(FieldError::new("scopes", "required", "At least one scope is required"));
+        }
+
+        if let Some(days) = self.expires_in_days {
+            if !(1..=validation::API_KEY_MAX_EXPIRY_DAYS).contains(&days) {
+                errors.push(
+                    FieldError::new(
+                        "expires_in_days",
+                        "out_of_range",
+                        format!(
+                            "Expiry must be between 1 and {} days",
+                            validation::API_KEY_MAX_EXPIRY_DAYS
+                        )
+                    )
+                );
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(CreateApiKey { name, scopes, expires_in_days: self.expires_in_days })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// An API key as listed; the key itself is only ever returned on creation.
+/// Times are Unix timestamps in seconds.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApiKeyResponse {
+    pub id: i64,
+    pub name: String,
+    /// The start of the key, to tell keys apart.
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+/// Response of `POST /api/keys`.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreatedApiKey {
+    /// Send as `X-Api-Key`. This is the only time it is shown.
+    pub key: String,
+    pub api_key: ApiKeyResponse,
+}
+
+/// Query string of `GET /api/users`. Unset fields are left out.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ListUsers {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_deleted: Option<bool>,
+    /// Unix timestamps bounding when users were created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<i64>,
+    /// A column name, prefixed with `-` for descending order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+}
+
+/// One page of a keyset-paginated listing. Cursors are opaque and only valid
+/// with the filters and sort they were issued for.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::ToSchema),
+    aliases(UserPage = Page<UserResponse>, AuditPage = Page<AuditEvent>)
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    pub total: i64,
+}
+
+/// A result of `GET /api/users/search`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchHit {
+    pub user: UserResponse,
+    pub rank: f32,
+    /// `name` and `email` with full-text matches wrapped in `<b>`...`</b>`.
+    /// The text itself is not HTML-escaped.
+    pub name_snippet: String,
+    pub email_snippet: String,
+}
+
+/// Who made a change.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ActorKind {
+    User,
+    ApiKey,
+    /// The server itself, e.g. purging deleted users.
+    System,
+}
+
+impl ActorKind {
+    pub const ALL: [ActorKind; 3] = [ActorKind::User, ActorKind::ApiKey, ActorKind::System];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActorKind::User => "user",
+            ActorKind::ApiKey => "api_key",
+            ActorKind::System => "system",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ActorKind> {
+        ActorKind::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+}
+
+/// What a change did.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum AuditAction {
+    #[serde(rename = "user.create")]
+    UserCreate,
+    #[serde(rename = "user.update")]
+    UserUpdate,
+    #[serde(rename = "user.set_role")]
+    UserSetRole,
+    /// A soft delete; the user can be restored until purged.
+    #[serde(rename = "user.delete")]
+    UserDelete,
+    #[serde(rename = "user.restore")]
+    UserRestore,
+    /// Removal of a deleted user once its retention period is over.
+    #[serde(rename = "user.purge")]
+    UserPurge,
+    /// A password set from the command line; hashes are never recorded.
+    #[serde(rename = "user.set_password")]
+    UserSetPassword,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 7] = [
+        AuditAction::UserCreate,
+        AuditAction::UserUpdate,
+        AuditAction::UserSetRole,
+        AuditAction::UserDelete,
+        AuditAction::UserRestore,
+        AuditAction::UserPurge,
+        AuditAction::UserSetPassword,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::UserCreate => "user.create",
+            AuditAction::UserUpdate => "user.update",
+            AuditAction::UserSetRole => "user.set_role",
+            AuditAction::UserDelete => "user.delete",
+            AuditAction::UserRestore => "user.restore",
+            AuditAction::UserPurge => "user.purge",
+            AuditAction::UserSetPassword => "user.set_password",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<AuditAction> {
+        AuditAction::ALL.into_iter().find(|action| action.as_str() == value)
+    }
+}
+
+/// A recorded change to a user, as returned by `GET /api/audit`. Times are
+/// Unix timestamps in seconds.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AuditEvent {
+    pub id: i64,
+    pub actor_kind: ActorKind,
+    pub actor_id: i64,
+    /// The actor's current name, if it still exists.
+    pub actor_name: Option<String>,
+    pub action: AuditAction,
+    /// Id of the user changed.
+    pub target_id: i32,
+    /// The user before the change; absent for creations.
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
+    pub before: Option<serde_json::Value>,
+    /// The user after the change; absent for purges.
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
+    pub after: Option<serde_json::Value>,
+    /// The `X-Request-Id` of the request that made the change.
+    pub request_id: String,
+    pub ip: Option<String>,
+    pub created_at: i64,
+}
+
+/// Query string of `GET /api/audit`. Unset fields are left out.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ListAudit {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_kind: Option<ActorKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<AuditAction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Unix timestamp; only events at or after it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<i64>,
+    /// Unix timestamp; only events before it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: impl Into<String>) -> Self {
+        FieldError { field: field.to_string(), code: code.to_string(), message: message.into() }
+    }
+}
+
+/// RFC 7807 `application/problem+json` body returned for every error.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    /// One of [`codes`].
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
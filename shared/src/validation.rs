@@ -0,0 +1,190 @@
+use crate::FieldError;
+
+pub const NAME_MAX_CHARS: usize = 100;
+// RFC 5321 limits for a forward path and its local part.
+pub const EMAIL_MAX_LEN: usize = 254;
+pub const EMAIL_LOCAL_MAX_LEN: usize = 64;
+pub const PASSWORD_MIN_CHARS: usize = 8;
+// Hashing cost grows with the input, so cap what a client can make us hash.
+pub const PASSWORD_MAX_LEN: usize = 1024;
+pub const API_KEY_MAX_EXPIRY_DAYS: u32 = 3650;
+pub const BULK_MAX_OPERATIONS: usize = 1000;
+pub const IMPORT_MAX_ROWS: usize = 10_000;
+
+pub fn validate_name(name: &str, errors: &mut Vec<FieldError>) {
+    if name.is_empty() {
+        errors.push(FieldError::new("name", "required", "Name is required"));
+    } else if name.chars().count() > NAME_MAX_CHARS {
+        errors.push(
+            FieldError::new(
+                "name",
+                "too_long",
+                format!("Name must be at most {} characters", NAME_MAX_CHARS)
+            )
+        );
+    } else if name.chars().any(char::is_control) {
+        errors.push(FieldError::new("name", "invalid", "Name cannot contain control characters"));
+    }
+}
+
+pub fn validate_email(email: &str, errors: &mut Vec<FieldError>) {
+    if email.is_empty() {
+        errors.push(FieldError::new("email", "required", "Email is required"));
+    } else if email.len() > EMAIL_MAX_LEN {
+        errors.push(
+            FieldError::new(
+                "email",
+                "too_long",
+                format!("Email must be at most {} characters", EMAIL_MAX_LEN)
+            )
+        );
+    } else if !is_valid_email(email) {
+        errors.push(FieldError::new("email", "invalid", "Email is not a valid address"));
+    }
+}
+
+pub fn validate_password(password: &str, errors: &mut Vec<FieldError>) {
+    if password.chars().count() < PASSWORD_MIN_CHARS {
+        errors.push(
+            FieldError::new(
+                "password",
+                "too_short",
+                format!("Password must be at least {} characters", PASSWORD_MIN_CHARS)
+            )
+        );
+    } else if password.len() > PASSWORD_MAX_LEN {
+        errors.push(
+            FieldError::new(
+                "password",
+                "too_long",
+                format!("Password must be at most {} bytes", PASSWORD_MAX_LEN)
+            )
+        );
+    }
+}
+
+/// Accepts the RFC 5322 `dot-atom@domain` form used by virtually every real
+/// mailbox; quoted local parts and address literals are rejected.
+pub fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+
+    let atext = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~-".contains(c);
+    let local_ok =
+        !local.is_empty() &&
+        local.len() <= EMAIL_LOCAL_MAX_LEN &&
+        local.split('.').all(|atom| !atom.is_empty() && atom.chars().all(atext));
+
+    let labels = domain.split('.').collect::<Vec<_>>();
+    let domain_ok =
+        labels.len() >= 2 &&
+        labels.iter().all(|label| {
+            (1..=63).contains(&label.len()) &&
+                !label.starts_with('-') &&
+                !label.ends_with('-') &&
+                label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        }) &&
+        !labels[labels.len() - 1].chars().all(|c| c.is_ascii_digit());
+
+    local_ok && domain_ok
+}
+
+/// Trims `name` and `email` and checks both, collecting every failure.
+pub fn validate_user(name: &str, email: &str) -> Result<(String, String), Vec<FieldError>> {
+    let (name, email) = (name.trim(), email.trim());
+
+    let mut errors = Vec::new();
+    validate_name(name, &mut errors);
+    validate_email(email, &mut errors);
+
+    if errors.is_empty() { Ok((name.to_string(), email.to_string())) } else { Err(errors) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(validate: fn(&str, &mut Vec<FieldError>), value: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        validate(value, &mut errors);
+        errors.into_iter().map(|error| error.code).collect()
+    }
+
+    #[test]
+    fn accepts_dot_atom_addresses() {
+        for email in [
+            "ada@example.com",
+            "ada.lovelace@example.com",
+            "ada+tag@mail.example.co.uk",
+            "o'brien@example.com",
+            "x@a-b.io",
+        ] {
+            assert!(is_valid_email(email), "{}", email);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_addresses() {
+        for email in [
+            "ada",
+            "@example.com",
+            "ada@",
+            ".ada@example.com",
+            "ada.@example.com",
+            "ada..lovelace@example.com",
+            "\"ada\"@example.com",
+            "ada@localhost",
+            "ada@[127.0.0.1]",
+            "ada@example..com",
+            "ada@-example.com",
+            "ada@example-.com",
+            "ada@exa_mple.com",
+            "ada@example.123",
+        ] {
+            assert!(!is_valid_email(email), "{}", email);
+        }
+    }
+
+    #[test]
+    fn limits_label_and_local_part_lengths() {
+        assert!(is_valid_email(&format!("ada@{}.com", "a".repeat(63))));
+        assert!(!is_valid_email(&format!("ada@{}.com", "a".repeat(64))));
+        assert!(is_valid_email(&format!("{}@example.com", "a".repeat(EMAIL_LOCAL_MAX_LEN))));
+        assert!(!is_valid_email(&format!("{}@example.com", "a".repeat(EMAIL_LOCAL_MAX_LEN + 1))));
+    }
+
+    #[test]
+    fn numeric_labels_are_allowed_before_the_tld() {
+        assert!(is_valid_email("ada@123.example.com"));
+        assert!(is_valid_email("ada@example.c0m"));
+    }
+
+    #[test]
+    fn validators_report_codes() {
+        assert_eq!(codes(validate_name, "Ada"), Vec::<String>::new());
+        assert_eq!(codes(validate_name, ""), ["required"]);
+        assert_eq!(codes(validate_name, &"é".repeat(NAME_MAX_CHARS)), Vec::<String>::new());
+        assert_eq!(codes(validate_name, &"a".repeat(NAME_MAX_CHARS + 1)), ["too_long"]);
+        assert_eq!(codes(validate_name, "Ada\nLovelace"), ["invalid"]);
+
+        assert_eq!(codes(validate_email, ""), ["required"]);
+        assert_eq!(codes(validate_email, &format!("{}@example.com", "a".repeat(EMAIL_MAX_LEN))), ["too_long"]);
+        assert_eq!(codes(validate_email, "ada"), ["invalid"]);
+
+        assert_eq!(codes(validate_password, "short"), ["too_short"]);
+        assert_eq!(codes(validate_password, "long enough"), Vec::<String>::new());
+        assert_eq!(codes(validate_password, &"a".repeat(PASSWORD_MAX_LEN + 1)), ["too_long"]);
+    }
+
+    #[test]
+    fn validate_user_trims_and_collects_every_error() {
+        assert_eq!(
+            validate_user("  Ada ", " ada@example.com\t"),
+            Ok(("Ada".to_string(), "ada@example.com".to_string()))
+        );
+        let errors = validate_user(" ", "ada").unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["name", "email"]);
+    }
+}